@@ -0,0 +1,97 @@
+use crate::pack::{Loader, PackInfo};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INSTANCE_FILE: &str = "instance.json";
+
+// ATLauncher's `instance.json` from before the update. It holds the resolved
+// version JSON (libraries, main class...) that the manager can't produce itself.
+pub struct Saved {
+    instance: Option<Value>,
+}
+
+// ATLauncher names instance folders after the instance with everything but
+// letters and digits removed, older instances may have been renamed since.
+pub fn instance_dir(instances: &Path, name: &str) -> PathBuf {
+    let named = fs::read_dir(instances)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|dir| {
+            fs::read_to_string(dir.join(INSTANCE_FILE))
+                .ok()
+                .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+                .is_some_and(|instance| instance["launcher"]["name"] == name)
+        });
+    named.unwrap_or_else(|| instances.join(safe_name(name)))
+}
+
+fn safe_name(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).collect()
+}
+
+pub fn capture(dir: &Path) -> Result<Saved> {
+    let path = dir.join(INSTANCE_FILE);
+    let instance = match path.is_file() {
+        true => {
+            let contents = fs::read_to_string(&path).context("Failed to read instance.json")?;
+            Some(serde_json::from_str(&contents).context("Failed to parse instance.json")?)
+        }
+        false => None,
+    };
+    Ok(Saved { instance })
+}
+
+pub fn register(dir: &Path, name: &str, info: &PackInfo, saved: Saved) -> Result<()> {
+    let Some(mut instance) = saved.instance else {
+        println!(
+            "ATLauncher has no instance called '{name}' yet. Create an empty instance with that name{}, then run the update again.",
+            describe(info).map_or(String::new(), |d| format!(" ({d})"))
+        );
+        return Ok(());
+    };
+
+    if !instance["launcher"].is_object() {
+        instance["launcher"] = json!({});
+    }
+    instance["launcher"]["name"] = json!(name);
+    instance["launcher"]["pack"] = json!(name);
+
+    // ATLauncher only downloads what its own version JSON lists, so a changed
+    // Minecraft or loader version has to be switched inside the launcher.
+    if let Some(minecraft) = &info.minecraft {
+        if instance["id"].as_str().is_some_and(|id| id != minecraft) {
+            println!("The pack now needs Minecraft {minecraft}, change it in ATLauncher's instance settings.");
+        }
+    }
+    if let Some((loader, version)) = &info.loader {
+        let current = &instance["launcher"]["loaderVersion"];
+        if current["version"] != version.as_str() || current["type"] != loader_type(*loader) {
+            println!("The pack now needs {loader} {version}, change the loader in ATLauncher's instance settings.");
+        }
+    }
+
+    let contents =
+        serde_json::to_string_pretty(&instance).context("Failed to serialize instance.json")?;
+    fs::write(dir.join(INSTANCE_FILE), contents).context("Failed to write instance.json")
+}
+
+fn loader_type(loader: Loader) -> &'static str {
+    match loader {
+        Loader::Forge => "Forge",
+        Loader::NeoForge => "NeoForge",
+        Loader::Fabric => "Fabric",
+        Loader::Quilt => "Quilt",
+    }
+}
+
+fn describe(info: &PackInfo) -> Option<String> {
+    let minecraft = info.minecraft.as_ref()?;
+    Some(match &info.loader {
+        Some((loader, version)) => format!("Minecraft {minecraft}, {loader} {version}"),
+        None => format!("Minecraft {minecraft}"),
+    })
+}
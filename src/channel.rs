@@ -0,0 +1,121 @@
+use crate::source::Source;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+use zip::ZipArchive;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    // The latest full release.
+    #[default]
+    Stable,
+    // The newest release, pre-releases included.
+    Beta,
+    // The newest build artifact of the repository's Actions workflow on its default branch.
+    Nightly,
+}
+
+// A nightly build, the files of its Actions artifact by file name.
+pub struct Nightly {
+    pub label: String,
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+// Actions artifacts are named either like the release asset or without `.zip`.
+pub async fn nightly(source: &Source, artifact_name: &str) -> Result<Option<Nightly>> {
+    let Some(github) = source.github() else {
+        bail!("Nightly builds are only available from GitHub");
+    };
+    if !github.authenticated() {
+        bail!("Nightly builds need a GitHub token (GITHUB_TOKEN, `github_token` or the keyring)");
+    }
+    let stem = artifact_name.trim_end_matches(".zip");
+    // Pull requests from forks and work on other branches upload artifacts
+    // too, only builds of the default branch of this repository count.
+    let repository = github.repository_info().await?;
+    let Some(artifact) = github
+        .artifacts()
+        .await?
+        .into_iter()
+        .filter(|a| {
+            a.workflow_run.as_ref().is_some_and(|run| {
+                run.head_branch.as_deref() == Some(repository.default_branch.as_str())
+                    && run.head_repository_id == Some(repository.id)
+            })
+        })
+        .find(|a| !a.expired && (a.name == artifact_name || a.name == stem))
+    else {
+        return Ok(None);
+    };
+
+    println!(
+        "Downloading nightly build {} ({})...",
+        artifact.id,
+        artifact.created_at.format("%Y-%m-%d %H:%M")
+    );
+    let wrapper = github.download_artifact(&artifact).await?;
+
+    Ok(Some(Nightly {
+        label: format!(
+            "nightly {} ({})",
+            artifact.id,
+            artifact.created_at.format("%Y-%m-%d")
+        ),
+        files: unpack(&wrapper)?,
+    }))
+}
+
+// GitHub wraps every artifact in a zip of its own, even when it is a zip already.
+fn unpack(wrapper: &[u8]) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive =
+        ZipArchive::new(io::Cursor::new(wrapper)).context("Failed to open workflow artifact")?;
+    let mut files = BTreeMap::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !file.is_file() {
+            continue;
+        }
+        let name = file
+            .name()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let mut content = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut content)
+            .with_context(|| format!("Failed to read '{}' from workflow artifact", name))?;
+        files.insert(name, content);
+    }
+    Ok(files)
+}
+
+impl FromStr for Channel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "stable" => Ok(Self::Stable),
+            "beta" => Ok(Self::Beta),
+            "nightly" => Ok(Self::Nightly),
+            _ => bail!(
+                "Unknown channel '{}', expected 'stable', 'beta' or 'nightly'",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Nightly => "nightly",
+        };
+        f.write_str(name)
+    }
+}
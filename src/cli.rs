@@ -0,0 +1,99 @@
+use crate::channel::Channel;
+use crate::launcher::Launcher;
+use anyhow::{bail, Context, Result};
+use std::env;
+use std::path::PathBuf;
+
+pub enum Command {
+    Update,
+    Restore { snapshot: Option<String> },
+    Uninstall,
+    List,
+    Releases,
+}
+
+pub struct Args {
+    pub command: Command,
+    // Only show what would change, don't touch the instance.
+    pub dry_run: bool,
+    // Skip confirmation prompts, exporting worlds and screenshots on uninstall.
+    pub yes: bool,
+    // Instance to work on instead of the configured one.
+    pub instance: Option<String>,
+    // Skips launcher detection.
+    pub launcher: Option<Launcher>,
+    // Instance directory to use as-is, bypassing the launcher lookup.
+    pub target: Option<PathBuf>,
+    // Release tag to install and pin, `latest` to unpin.
+    pub tag: Option<String>,
+    // Update channel instead of the configured one.
+    pub channel: Option<Channel>,
+    // `owner/name` of the repository to take releases from.
+    pub repo: Option<String>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut dry_run = false;
+        let mut yes = false;
+        let mut instance = None;
+        let mut launcher = None;
+        let mut target = None;
+        let mut tag = None;
+        let mut channel = None;
+        let mut repo = None;
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .with_context(|| format!("Option '{}' needs a value", flag))
+            };
+
+            match flag {
+                "--dry-run" | "-n" => dry_run = true,
+                "--yes" | "-y" => yes = true,
+                "--instance" | "-i" => instance = Some(value()?),
+                "--launcher" | "-l" => launcher = Some(value()?.parse()?),
+                "--target" | "-t" => target = Some(PathBuf::from(value()?)),
+                "--tag" => tag = Some(value()?),
+                "--channel" | "-c" => channel = Some(value()?.parse()?),
+                "--repo" | "-r" => repo = Some(value()?),
+                flag if flag.starts_with('-') => bail!("Unknown option '{}'", flag),
+                _ => positional.push(arg),
+            }
+        }
+
+        let command = match positional.first().map(String::as_str) {
+            None | Some("update") => Command::Update,
+            Some("restore") => Command::Restore {
+                snapshot: positional.get(1).cloned(),
+            },
+            Some("uninstall") => Command::Uninstall,
+            Some("list") => Command::List,
+            Some("releases") => Command::Releases,
+            Some(other) => bail!(
+                "Unknown command '{}', expected 'update', 'restore', 'uninstall', 'list' or 'releases'",
+                other
+            ),
+        };
+        Ok(Self {
+            command,
+            dry_run,
+            yes,
+            instance,
+            launcher,
+            target,
+            tag,
+            channel,
+            repo,
+        })
+    }
+}
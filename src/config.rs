@@ -0,0 +1,96 @@
+use crate::channel::Channel;
+use crate::modrinth::ModrinthSettings;
+use crate::pack::PackOverrides;
+use crate::prism::PrismSettings;
+use crate::server::ServerSettings;
+use crate::source::SourceSettings;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = "originalife-manager.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // Extra globs on top of the built-in preserve list, relative to the game directory.
+    pub preserve: Vec<String>,
+    // Where snapshots and other manager state are kept.
+    pub data_dir: PathBuf,
+    // How many pre-update snapshots to keep per instance.
+    pub snapshot_keep: usize,
+    // Refuse to install releases that do not publish a checksum for the artifact.
+    pub require_checksum: bool,
+    // minisign public key releases must be signed with, overrides the built-in one.
+    pub public_key: Option<String>,
+    // `owner/name` of the repository releases come from, overrides the built-in one.
+    pub repository: Option<String>,
+    // Where releases are looked up: GitHub, Gitea/Forgejo, GitLab or a static index.
+    pub source: SourceSettings,
+    // GitHub token, used when `GITHUB_TOKEN` is not set.
+    pub github_token: Option<String>,
+    // Fallbacks for release downloads, HTTP base URLs or directories holding `<tag>/<artifact>`.
+    pub mirrors: Vec<String>,
+    // Seconds a single download attempt may take before the next mirror is tried.
+    pub download_timeout: u64,
+    // Which builds `update` installs: stable, beta or nightly.
+    pub channel: Channel,
+    // Instance directory name used when `--instance` is not given.
+    pub instance_name: String,
+    // Fixed instance directory, skips the launcher lookup like `--target`.
+    pub target_dir: Option<PathBuf>,
+    // Minecraft and mod loader versions to use instead of the pack's own metadata.
+    pub pack: PackOverrides,
+    pub prism: PrismSettings,
+    pub modrinth: ModrinthSettings,
+    pub server: ServerSettings,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            preserve: Vec::new(),
+            data_dir: PathBuf::from("originalife-manager"),
+            snapshot_keep: 3,
+            require_checksum: false,
+            public_key: None,
+            repository: None,
+            source: SourceSettings::default(),
+            github_token: None,
+            mirrors: Vec::new(),
+            download_timeout: 600,
+            channel: Channel::Stable,
+            instance_name: "Originalife Season 4".to_string(),
+            target_dir: None,
+            pack: PackOverrides::default(),
+            prism: PrismSettings::default(),
+            modrinth: ModrinthSettings::default(),
+            server: ServerSettings::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Self> {
+        let path = Path::new(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path).context("Failed to read config file")?;
+        serde_json::from_str(&contents).context("Failed to parse config file")
+    }
+
+    // Instances of the same name can live in several launchers, the folder
+    // also carries a short hash of the instance directory to keep them apart.
+    pub fn snapshot_dir(&self, name: &str, dir: &Path) -> PathBuf {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let hash = Sha256::digest(dir.to_string_lossy().as_bytes());
+        let hex: String = hash[..4].iter().map(|b| format!("{b:02x}")).collect();
+        self.data_dir
+            .join("snapshots")
+            .join(format!("{}-{}", name, hex))
+    }
+}
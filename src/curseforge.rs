@@ -0,0 +1,180 @@
+use crate::pack::{Loader, PackInfo};
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+const INSTANCE_FILE: &str = "minecraftinstance.json";
+// The CurseForge pack manifest, lists the addons by project and file id.
+const PACK_MANIFEST: &str = "manifest.json";
+// CurseForge's game id for Minecraft.
+const MINECRAFT_GAME_ID: u64 = 432;
+
+// The app's own `minecraftinstance.json` from before the update; it holds play
+// time, memory and Java overrides, and addon details the pack doesn't ship.
+pub struct Saved {
+    instance: Option<Value>,
+}
+
+pub fn capture(dir: &Path) -> Result<Saved> {
+    Ok(Saved {
+        instance: read_json(&dir.join(INSTANCE_FILE))?,
+    })
+}
+
+pub fn register(dir: &Path, name: &str, info: &PackInfo, saved: Saved) -> Result<()> {
+    let shipped = read_json(&dir.join(INSTANCE_FILE))?;
+    let mut instance = match saved.instance.or(shipped) {
+        Some(Value::Object(instance)) => instance,
+        _ => Map::new(),
+    };
+
+    instance.insert("name".into(), json!(name));
+    instance.insert("gameTypeID".into(), json!(MINECRAFT_GAME_ID));
+    instance.insert("installPath".into(), json!(install_path(dir)?));
+    instance.insert("isVanilla".into(), json!(info.loader.is_none()));
+    instance
+        .entry("guid")
+        .or_insert_with(|| json!(instance_guid(dir)));
+    instance
+        .entry("installDate")
+        .or_insert_with(|| json!(chrono::Utc::now().to_rfc3339()));
+    if let Some(minecraft) = &info.minecraft {
+        instance.insert("gameVersion".into(), json!(minecraft));
+    }
+    if let (Some(minecraft), Some((loader, version))) = (&info.minecraft, &info.loader) {
+        let current = instance.get("baseModLoader").cloned().unwrap_or_default();
+        instance.insert(
+            "baseModLoader".into(),
+            base_mod_loader(current, minecraft, *loader, version),
+        );
+    }
+
+    let previous = instance
+        .get("installedAddons")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let manifest = read_json(&dir.join(PACK_MANIFEST))?;
+    instance.insert(
+        "installedAddons".into(),
+        json!(installed_addons(dir, &previous, manifest.as_ref())),
+    );
+
+    let contents = serde_json::to_string_pretty(&Value::Object(instance))
+        .context("Failed to serialize minecraftinstance.json")?;
+    fs::write(dir.join(INSTANCE_FILE), contents).context("Failed to write minecraftinstance.json")
+}
+
+fn read_json(path: &Path) -> Result<Option<Value>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .map(Some)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+// CurseForge stores the directory with a trailing separator.
+fn install_path(dir: &Path) -> Result<String> {
+    let path = std::path::absolute(dir).context("Failed to resolve instance directory")?;
+    Ok(format!("{}{}", path.display(), std::path::MAIN_SEPARATOR))
+}
+
+// Stable per directory, so re-registering doesn't make the app see a new instance.
+fn instance_guid(dir: &Path) -> String {
+    let hash = Sha256::digest(dir.to_string_lossy().as_bytes());
+    let hex: String = hash[..16].iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn base_mod_loader(current: Value, minecraft: &str, loader: Loader, version: &str) -> Value {
+    let name = format!("{loader}-{version}");
+    // Keep what the app resolved (install method, version JSON...) if it still matches.
+    if current["name"] == name.as_str() && current["minecraftVersion"] == minecraft {
+        return current;
+    }
+    let loader_type = match loader {
+        Loader::Forge => 1,
+        Loader::Fabric => 4,
+        Loader::Quilt => 5,
+        Loader::NeoForge => 6,
+    };
+    json!({
+        "name": name,
+        "type": loader_type,
+        "forgeVersion": version,
+        "minecraftVersion": minecraft,
+    })
+}
+
+// Addons as the pack manifest lists them, reusing the app's details for files
+// that are still installed and dropping entries whose files are gone.
+fn installed_addons(dir: &Path, previous: &[Value], manifest: Option<&Value>) -> Vec<Value> {
+    let present: HashSet<String> = ["mods", "resourcepacks", "shaderpacks"]
+        .iter()
+        .filter_map(|sub| fs::read_dir(dir.join(sub)).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    let installed = |addon: &Value| {
+        addon["installedFile"]["fileName"]
+            .as_str()
+            .is_some_and(|name| present.contains(name))
+    };
+
+    let Some(files) = manifest.and_then(|m| m["files"].as_array()) else {
+        return previous.iter().filter(|a| installed(a)).cloned().collect();
+    };
+
+    let mut addons = Vec::new();
+    for file in files {
+        let (Some(project), Some(file_id)) = (file["projectID"].as_u64(), file["fileID"].as_u64())
+        else {
+            continue;
+        };
+        let known = previous.iter().find(|a| {
+            a["addonID"].as_u64() == Some(project)
+                && a["installedFile"]["id"].as_u64() == Some(file_id)
+                && installed(a)
+        });
+        addons.push(match known {
+            Some(addon) => addon.clone(),
+            None => json!({
+                "addonID": project,
+                "gameID": MINECRAFT_GAME_ID,
+                "installedFile": { "id": file_id, "projectId": project },
+                "status": 0,
+            }),
+        });
+    }
+    // Addons the player added in the app themselves.
+    let listed: HashSet<u64> = addons
+        .iter()
+        .filter_map(|a| a["addonID"].as_u64())
+        .collect();
+    addons.extend(
+        previous
+            .iter()
+            .filter(|a| {
+                a["addonID"]
+                    .as_u64()
+                    .is_some_and(|id| !listed.contains(&id))
+            })
+            .filter(|a| installed(a))
+            .cloned(),
+    );
+    addons
+}
@@ -0,0 +1,283 @@
+use crate::channel::Nightly;
+use crate::config::Config;
+use crate::server;
+use crate::source::Release;
+use crate::verify;
+use anyhow::{bail, Context, Result};
+use http::HeaderMap;
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::Url;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+// A download that hasn't produced a byte for this long counts as failed.
+const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The server's Content-Length isn't trusted with more memory up front than this.
+const MAX_PREALLOCATION: u64 = 64 * 1024 * 1024;
+
+// The pack artifact of a release or nightly build, with the files that come with it.
+pub struct Download {
+    pub source: String,
+    pub content: Vec<u8>,
+    // SHA-256 the content was verified against, if the release publishes one.
+    pub checksum: Option<String>,
+    pub signature: Option<String>,
+    pub client_only: Option<String>,
+}
+
+// Somewhere a copy of the artifact may be found, the release itself or a mirror.
+// Only the release's own copy gets the source's headers, mirrors never see a token.
+enum Location {
+    Http(Url, HeaderMap),
+    File(PathBuf),
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(url, _) => write!(f, "{}", url),
+            Self::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// Mirrors keep artifacts as `<mirror>/<tag>/<artifact>`, over HTTP or on a file share.
+fn mirror_location(mirror: &str, tag: &str, artifact_name: &str) -> Result<Location> {
+    if mirror.starts_with("http://") || mirror.starts_with("https://") {
+        let base = Url::parse(&format!("{}/", mirror.trim_end_matches('/')))
+            .with_context(|| format!("Invalid mirror URL '{}'", mirror))?;
+        let url = base
+            .join(&format!("{tag}/{artifact_name}"))
+            .with_context(|| format!("Invalid mirror URL '{}'", mirror))?;
+        return Ok(Location::Http(url, HeaderMap::new()));
+    }
+    let base = match Url::parse(mirror) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|_| anyhow::anyhow!("Invalid mirror path '{}'", mirror))?,
+        _ => PathBuf::from(mirror),
+    };
+    Ok(Location::File(base.join(tag).join(artifact_name)))
+}
+
+// Downloads `artifact_name` from the release, falling back to the configured
+// mirrors in order. Every copy has to match the release's size and checksum.
+pub async fn release(
+    release: &Release,
+    artifact_name: &str,
+    config: &Config,
+) -> Result<Option<Download>> {
+    let Some(asset) = release.asset(artifact_name) else {
+        return Ok(None);
+    };
+    let client = reqwest::Client::new();
+    let timeout = Duration::from_secs(config.download_timeout);
+    let signed = verify::public_key(config.public_key.as_deref())?.is_some();
+
+    // The release's copy first, then each mirror's `<tag>/<name>`.
+    let locations = |name: &str, mirrors: bool| -> Result<Vec<Location>> {
+        let mut locations = Vec::new();
+        if let Some(asset) = release.asset(name) {
+            let url = Url::from_str(&asset.url)
+                .with_context(|| format!("Invalid download URL for '{}'", name))?;
+            locations.push(Location::Http(url, asset.headers.clone()));
+        }
+        if mirrors {
+            for mirror in &config.mirrors {
+                locations.push(mirror_location(mirror, &release.tag, name)?);
+            }
+        }
+        Ok(locations)
+    };
+    let text = |content: Vec<u8>| String::from_utf8_lossy(&content).into_owned();
+
+    let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+    let checksum = match verify::checksum_file(&names, artifact_name) {
+        Some(name) => {
+            // A mirror could hand out a checksum matching its own bad copy,
+            // only the signature check would catch that.
+            let contents = fetch_first(
+                &client,
+                &locations(name, signed)?,
+                timeout,
+                "checksums",
+                None,
+                |_| Ok(()),
+            )
+            .await?;
+            let expected = verify::expected_checksum(&text(contents), artifact_name)
+                .with_context(|| format!("'{}' has no entry for '{}'", name, artifact_name))?;
+            Some(expected)
+        }
+        None => None,
+    };
+    let signature_name = verify::signature_file(artifact_name);
+    let signature = match release.asset(&signature_name) {
+        Some(_) => {
+            let locations = locations(&signature_name, true)?;
+            let content =
+                fetch_first(&client, &locations, timeout, "signature", None, |_| Ok(())).await?;
+            Some(text(content))
+        }
+        None => None,
+    };
+    let client_only = match release.asset(server::CLIENT_ONLY_LIST) {
+        Some(_) => {
+            let locations = locations(server::CLIENT_ONLY_LIST, true)?;
+            let what = "the client-only mod list";
+            let content = fetch_first(&client, &locations, timeout, what, None, |_| Ok(())).await?;
+            Some(text(content))
+        }
+        None => None,
+    };
+
+    // Without a checksum there is nothing to tell a good mirror copy from a bad one.
+    if checksum.is_none() && !config.mirrors.is_empty() {
+        println!("The release publishes no checksum, mirrors won't be used")
+    }
+    let content = fetch_first(
+        &client,
+        &locations(artifact_name, checksum.is_some())?,
+        timeout,
+        artifact_name,
+        Some(asset.size),
+        |content| {
+            if let Some(size) = asset.size {
+                verify::verify_size(content, size, artifact_name)?;
+            }
+            if let Some(expected) = &checksum {
+                verify::verify_checksum(content, expected, artifact_name)?;
+            }
+            Ok(())
+        },
+    )
+    .await?;
+    if checksum.is_some() {
+        println!("Verified SHA-256 checksum of {}", artifact_name);
+    }
+
+    Ok(Some(Download {
+        source: release.tag.clone(),
+        content,
+        checksum,
+        signature,
+        client_only,
+    }))
+}
+
+// Tries each location in turn until one delivers a copy that passes `check`
+// within `timeout`. Only downloads with a `progress` size hint show a progress bar.
+async fn fetch_first(
+    client: &reqwest::Client,
+    locations: &[Location],
+    timeout: Duration,
+    what: &str,
+    progress: Option<Option<u64>>,
+    check: impl Fn(&[u8]) -> Result<()>,
+) -> Result<Vec<u8>> {
+    for (i, location) in locations.iter().enumerate() {
+        if i > 0 {
+            println!("Trying mirror {}", location);
+        }
+        let attempt = async {
+            let content = match location {
+                Location::Http(url, headers) => fetch_http(client, url, headers, progress).await?,
+                Location::File(path) => tokio::fs::read(path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            };
+            check(&content)?;
+            Ok::<_, anyhow::Error>(content)
+        };
+        match tokio::time::timeout(timeout, attempt).await {
+            Ok(Ok(content)) => return Ok(content),
+            Ok(Err(error)) => println!("Download from {} failed: {:#}", location, error),
+            Err(_) => println!(
+                "Download from {} took longer than {}s, giving up on it",
+                location,
+                timeout.as_secs()
+            ),
+        }
+    }
+    bail!("Could not download {} from any source", what)
+}
+
+async fn fetch_http(
+    client: &reqwest::Client,
+    url: &Url,
+    headers: &HeaderMap,
+    progress: Option<Option<u64>>,
+) -> Result<Vec<u8>> {
+    let mut response = client
+        .get(url.clone())
+        .headers(headers.clone())
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .context("Failed to send request")?;
+    // Not every source reports asset sizes, fall back to what the server says.
+    let total_size = progress
+        .flatten()
+        .or(response.content_length())
+        .unwrap_or(0);
+
+    let pb = match progress {
+        Some(_) => ProgressBar::new(total_size),
+        None => ProgressBar::hidden(),
+    };
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+        .expect("fuck.")
+        .progress_chars("#>-"));
+
+    let mut content = Vec::with_capacity(total_size.min(MAX_PREALLOCATION) as usize);
+
+    loop {
+        let chunk = tokio::time::timeout(STALL_TIMEOUT, response.chunk())
+            .await
+            .context("Download stalled")?
+            .context("Failed to read chunk")?;
+        let Some(chunk) = chunk else {
+            break;
+        };
+        content.extend_from_slice(&chunk);
+        pb.inc(chunk.len() as u64);
+    }
+
+    pb.finish_with_message("Download completed");
+    Ok(content)
+}
+
+pub fn nightly(mut nightly: Nightly, artifact_name: &str) -> Result<Option<Download>> {
+    let Some(content) = nightly.files.remove(artifact_name) else {
+        return Ok(None);
+    };
+    let text = |name: &str| {
+        nightly
+            .files
+            .get(name)
+            .map(|c| String::from_utf8_lossy(c).into_owned())
+    };
+    let names: Vec<&str> = nightly.files.keys().map(String::as_str).collect();
+    let checksum = match verify::checksum_file(&names, artifact_name) {
+        Some(name) => {
+            let expected = text(name)
+                .and_then(|contents| verify::expected_checksum(&contents, artifact_name))
+                .with_context(|| format!("'{}' has no entry for '{}'", name, artifact_name))?;
+            verify::verify_checksum(&content, &expected, artifact_name)?;
+            println!("Verified SHA-256 checksum of {}", artifact_name);
+            Some(expected)
+        }
+        None => None,
+    };
+    Ok(Some(Download {
+        checksum,
+        signature: text(&verify::signature_file(artifact_name)),
+        client_only: text(server::CLIENT_ONLY_LIST),
+        source: nightly.label,
+        content,
+    }))
+}
@@ -0,0 +1,327 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use zip::read::ZipFile;
+use zip::ZipArchive;
+
+// Entries that inflate by more than this factor are treated as zip bombs.
+const MAX_RATIO: u64 = 200;
+// Small entries compress well no matter what, don't hold them to the ratio.
+const RATIO_MIN_SIZE: u64 = 1024 * 1024;
+const MAX_ENTRY_SIZE: u64 = 4 * 1024 * 1024 * 1024;
+const MAX_TOTAL_SIZE: u64 = 32 * 1024 * 1024 * 1024;
+
+const S_IFMT: u32 = 0o170000;
+const S_IFREG: u32 = 0o100000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFLNK: u32 = 0o120000;
+
+// A regular file in the archive that is safe to write under the instance.
+pub struct Entry {
+    pub index: usize,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+// Returns the archive's safe file entries, reporting every entry it rejects.
+pub fn scan<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut rejected = 0;
+    let mut total: u64 = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        let name = file.name().to_string();
+        match check(&mut file) {
+            Ok(Some(path)) => {
+                total += file.size();
+                entries.push(Entry {
+                    index,
+                    path,
+                    size: file.size(),
+                });
+            }
+            Ok(None) => {}
+            Err(reason) => {
+                println!("Rejected archive entry '{}': {}", name, reason);
+                rejected += 1;
+            }
+        }
+    }
+
+    if total > MAX_TOTAL_SIZE {
+        bail!(
+            "Archive would extract to {} bytes, more than the {} byte limit",
+            total,
+            MAX_TOTAL_SIZE
+        );
+    }
+    if rejected > 0 {
+        println!("Skipped {} unsafe archive entries", rejected);
+    }
+    Ok(entries)
+}
+
+// `Ok(None)` for directories, which need no extraction.
+fn check(file: &mut ZipFile<'_>) -> Result<Option<PathBuf>, String> {
+    let path = safe_path(file.name())?;
+
+    match file.unix_mode().map(|mode| mode & S_IFMT) {
+        None | Some(0) | Some(S_IFREG) => {}
+        Some(S_IFDIR) => return Ok(None),
+        Some(S_IFLNK) => {
+            let mut target = String::new();
+            file.take(4096)
+                .read_to_string(&mut target)
+                .map_err(|e| format!("unreadable symlink ({e})"))?;
+            return Err(match escapes(&path, &target) {
+                true => format!("symlink to '{target}' points outside the instance"),
+                false => format!("symlink to '{target}', symlinks are not extracted"),
+            });
+        }
+        Some(_) => return Err("device, FIFO or socket entry".to_string()),
+    }
+    if file.is_dir() {
+        return Ok(None);
+    }
+
+    let size = file.size();
+    let compressed = file.compressed_size().max(1);
+    if size > MAX_ENTRY_SIZE {
+        return Err(format!(
+            "{size} bytes is larger than the {MAX_ENTRY_SIZE} byte limit"
+        ));
+    }
+    if size > RATIO_MIN_SIZE && size / compressed > MAX_RATIO {
+        return Err(format!(
+            "compression ratio of {} exceeds {MAX_RATIO}",
+            size / compressed
+        ));
+    }
+
+    Ok(Some(path))
+}
+
+fn safe_path(name: &str) -> Result<PathBuf, String> {
+    if name.contains('\0') {
+        return Err("name contains a NUL byte".to_string());
+    }
+    let normalized = name.replace('\\', "/");
+    if normalized.starts_with('/') || normalized.as_bytes().get(1) == Some(&b':') {
+        return Err("absolute path".to_string());
+    }
+
+    let mut path = PathBuf::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err("path traversal with '..'".to_string()),
+            part => path.push(part),
+        }
+    }
+    // Catches anything the platform still parses as a prefix or root.
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err("absolute path".to_string());
+    }
+    if path.as_os_str().is_empty() {
+        return Err("empty path".to_string());
+    }
+    Ok(path)
+}
+
+fn escapes(link: &Path, target: &str) -> bool {
+    let target = target.replace('\\', "/");
+    if target.starts_with('/') || target.as_bytes().get(1) == Some(&b':') {
+        return true;
+    }
+
+    let mut depth = link.components().count() as i64 - 1;
+    for part in target.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => depth -= 1,
+            _ => depth += 1,
+        }
+        if depth < 0 {
+            return true;
+        }
+    }
+    false
+}
+
+// Writes an entry found by `scan`, refusing to inflate past its declared size.
+pub fn write_entry<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entry: &Entry,
+    out_path: &Path,
+) -> Result<()> {
+    let file = archive.by_index(entry.index)?;
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = fs::File::create(out_path)
+        .with_context(|| format!("Failed to create {}", out_path.display()))?;
+    let written = io::copy(&mut file.take(entry.size + 1), &mut out)?;
+    if written > entry.size {
+        bail!("'{}' inflates past its declared size", entry.path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    const CENTRAL_HEADER: &[u8] = b"PK\x01\x02";
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (name, content) in files {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    // Overwrites a little-endian u32 in the central directory record of `name`,
+    // for headers `ZipWriter` won't produce.
+    fn patch(bytes: &mut [u8], name: &str, offset: usize, value: u32) {
+        let start = (0..bytes.len())
+            .find(|&i| {
+                bytes[i..].starts_with(CENTRAL_HEADER)
+                    && bytes[i + 46..].starts_with(name.as_bytes())
+            })
+            .expect("central directory record");
+        bytes[start + offset..start + offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn scan_paths(bytes: Vec<u8>) -> Vec<PathBuf> {
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        scan(&mut archive)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect()
+    }
+
+    #[test]
+    fn safe_path_rejects_escapes() {
+        assert!(safe_path("../x").is_err());
+        assert!(safe_path("mods/../../x").is_err());
+        assert!(safe_path("/abs").is_err());
+        assert!(safe_path("C:x").is_err());
+        assert!(safe_path(r"C:\Windows\x").is_err());
+        assert!(safe_path(r"mods\..\..\x").is_err());
+        assert!(safe_path(r"\abs").is_err());
+        assert!(safe_path("a\0b").is_err());
+        assert!(safe_path("./").is_err());
+    }
+
+    #[test]
+    fn safe_path_normalizes() {
+        assert_eq!(safe_path("mods/a.jar"), Ok(PathBuf::from("mods/a.jar")));
+        assert_eq!(
+            safe_path(r"config\b.toml"),
+            Ok(PathBuf::from("config/b.toml"))
+        );
+        assert_eq!(safe_path("./mods//a.jar"), Ok(PathBuf::from("mods/a.jar")));
+    }
+
+    #[test]
+    fn escapes_follows_the_link_location() {
+        assert!(!escapes(Path::new("mods/link"), "a.jar"));
+        assert!(!escapes(Path::new("mods/link"), "../config/a.toml"));
+        assert!(escapes(Path::new("mods/link"), "../../x"));
+        assert!(escapes(Path::new("link"), "../x"));
+        assert!(escapes(Path::new("mods/link"), "/etc/passwd"));
+        assert!(escapes(Path::new("mods/link"), r"C:\x"));
+        assert!(escapes(Path::new("mods/link"), r"..\..\x"));
+        assert!(escapes(Path::new("mods/link"), "a/../../../x"));
+    }
+
+    #[test]
+    fn scan_skips_unsafe_names() {
+        let bytes = archive(&[
+            ("mods/a.jar", b"a"),
+            ("../x", b"x"),
+            ("/abs", b"x"),
+            ("C:x", b"x"),
+            (r"mods\..\..\x", b"x"),
+        ]);
+        assert_eq!(scan_paths(bytes), [PathBuf::from("mods/a.jar")]);
+    }
+
+    #[test]
+    fn scan_skips_symlinks() {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        zip.start_file("mods/a.jar", options).unwrap();
+        zip.write_all(b"a").unwrap();
+        zip.add_symlink("mods/inside", "a.jar", options).unwrap();
+        zip.add_symlink("mods/outside", "../../../etc/passwd", options)
+            .unwrap();
+        let bytes = zip.finish().unwrap().into_inner();
+        assert_eq!(scan_paths(bytes), [PathBuf::from("mods/a.jar")]);
+    }
+
+    #[test]
+    fn scan_skips_device_entries() {
+        let mut bytes = archive(&[("mods/a.jar", b"a"), ("dev", b"")]);
+        // External attributes, the unix mode sits in the upper half.
+        patch(&mut bytes, "dev", 38, 0o020644 << 16);
+        assert_eq!(scan_paths(bytes), [PathBuf::from("mods/a.jar")]);
+    }
+
+    #[test]
+    fn scan_skips_zip_bombs() {
+        let zeros = vec![0; 4 * 1024 * 1024];
+        let bytes = archive(&[("mods/a.jar", b"a"), ("bomb", &zeros)]);
+        assert_eq!(scan_paths(bytes), [PathBuf::from("mods/a.jar")]);
+    }
+
+    #[test]
+    fn small_entries_are_not_held_to_the_ratio() {
+        let zeros = vec![0; 512 * 1024];
+        let bytes = archive(&[("config/zeros", &zeros)]);
+        assert_eq!(scan_paths(bytes), [PathBuf::from("config/zeros")]);
+    }
+
+    #[test]
+    fn write_entry_stops_at_the_declared_size() {
+        let mut bytes = archive(&[("config/a.txt", b"more than declared")]);
+        // Uncompressed size.
+        patch(&mut bytes, "config/a.txt", 24, 4);
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let entries = scan(&mut archive).unwrap();
+        assert_eq!(entries[0].size, 4);
+
+        let dir = std::env::temp_dir().join(format!("extract-test-{}", std::process::id()));
+        let out = dir.join("a.txt");
+        let result = write_entry(&mut archive, &entries[0], &out);
+        let _ = fs::remove_dir_all(&dir);
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("inflates past its declared size"), "{error}");
+    }
+
+    #[test]
+    fn write_entry_writes_the_content() {
+        let bytes = archive(&[("config/a.txt", b"hello")]);
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let entries = scan(&mut archive).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("extract-ok-{}", std::process::id()));
+        let out = dir.join("config/a.txt");
+        write_entry(&mut archive, &entries[0], &out).unwrap();
+        let content = fs::read(&out).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(content, b"hello");
+    }
+}
@@ -0,0 +1,68 @@
+use crate::pack::{Loader, PackInfo};
+use anyhow::{Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+
+const CONFIG_FILE: &str = "config.json";
+
+// GDLauncher's `config.json` from before the update; play time, Java and
+// memory overrides and the mod list live there.
+pub struct Saved {
+    config: Option<Value>,
+}
+
+pub fn capture(dir: &Path) -> Result<Saved> {
+    let path = dir.join(CONFIG_FILE);
+    let config = match path.is_file() {
+        true => {
+            let contents = fs::read_to_string(&path).context("Failed to read config.json")?;
+            Some(serde_json::from_str(&contents).context("Failed to parse config.json")?)
+        }
+        false => None,
+    };
+    Ok(Saved { config })
+}
+
+pub fn register(dir: &Path, info: &PackInfo, saved: Saved) -> Result<()> {
+    let mut config = match saved.config {
+        Some(Value::Object(config)) => config,
+        _ => Map::new(),
+    };
+    let minecraft = info.require_minecraft()?;
+
+    // GDLauncher installs the loader itself on launch from this block.
+    let mut loader = match config.remove("loader") {
+        Some(Value::Object(loader)) => loader,
+        _ => Map::new(),
+    };
+    loader.insert("mcVersion".into(), json!(minecraft));
+    match &info.loader {
+        Some((kind, version)) => {
+            if matches!(kind, Loader::NeoForge | Loader::Quilt) {
+                println!("GDLauncher may not support {kind}, the instance might not start.");
+            }
+            loader.insert("loaderType".into(), json!(kind.to_string()));
+            loader.insert("loaderVersion".into(), json!(version));
+        }
+        None => {
+            loader.insert("loaderType".into(), json!("vanilla"));
+            loader.remove("loaderVersion");
+        }
+    }
+    config.insert("loader".into(), Value::Object(loader));
+
+    // Drop mod entries whose files the update removed.
+    if let Some(Value::Array(mods)) = config.get_mut("mods") {
+        mods.retain(|m| {
+            m["fileName"]
+                .as_str()
+                .is_some_and(|name| dir.join("mods").join(name).is_file())
+        });
+    }
+    config.entry("timePlayed").or_insert_with(|| json!(0));
+
+    let contents = serde_json::to_string_pretty(&Value::Object(config))
+        .context("Failed to serialize config.json")?;
+    fs::write(dir.join(CONFIG_FILE), contents).context("Failed to write config.json")
+}
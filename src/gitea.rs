@@ -0,0 +1,120 @@
+use crate::repository::Repository;
+use crate::source::{self, Asset, Release};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+const PAGE_SIZE: usize = 50;
+
+// Releases from a Gitea or Forgejo instance, e.g. a self-hosted mirror of the pack.
+pub struct Gitea {
+    client: reqwest::Client,
+    api: String,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GiteaRelease {
+    tag_name: String,
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    assets: Vec<GiteaAsset>,
+}
+
+#[derive(Deserialize)]
+struct GiteaAsset {
+    name: String,
+    size: u64,
+    browser_download_url: String,
+}
+
+impl Gitea {
+    pub fn new(url: &str, repository: &Repository, token: Option<String>) -> Result<Self> {
+        if repository.is_nested() {
+            bail!(
+                "Gitea repositories are 'owner/name', '{}' is nested",
+                repository
+            );
+        }
+        Ok(Self {
+            client: reqwest::Client::new(),
+            api: format!(
+                "{}/api/v1/repos/{}/{}",
+                url.trim_end_matches('/'),
+                source::path_segment(&repository.owner),
+                source::path_segment(&repository.name)
+            ),
+            token,
+        })
+    }
+
+    fn authorization(&self) -> Option<String> {
+        self.token.as_ref().map(|token| format!("token {token}"))
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut request = self.client.get(format!("{}{}", self.api, path));
+        if let Some(authorization) = self.authorization() {
+            request = request.header("Authorization", authorization);
+        }
+        source::get_json(request).await
+    }
+
+    fn convert(&self, release: GiteaRelease) -> Release {
+        Release {
+            tag: release.tag_name,
+            published_at: release.published_at,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|a| Asset {
+                    headers: source::token_headers(
+                        &a.browser_download_url,
+                        &self.api,
+                        "Authorization",
+                        self.authorization(),
+                    ),
+                    name: a.name,
+                    size: Some(a.size),
+                    url: a.browser_download_url,
+                })
+                .collect(),
+        }
+    }
+
+    pub async fn latest_release(&self) -> Result<Release> {
+        let release: GiteaRelease = self
+            .get("/releases/latest")
+            .await
+            .context("Failed to fetch latest release")?;
+        Ok(self.convert(release))
+    }
+
+    pub async fn release(&self, tag: &str) -> Result<Release> {
+        let release: GiteaRelease = self
+            .get(&format!("/releases/tags/{}", source::path_segment(tag)))
+            .await?;
+        Ok(self.convert(release))
+    }
+
+    pub async fn releases(&self, pages: usize) -> Result<Vec<Release>> {
+        let mut releases = Vec::new();
+        for page in 1..=pages {
+            let batch: Vec<GiteaRelease> = self
+                .get(&format!("/releases?limit={PAGE_SIZE}&page={page}"))
+                .await?;
+            let last = batch.len() < PAGE_SIZE;
+            releases.extend(batch.into_iter().map(|r| self.convert(r)));
+            if last {
+                break;
+            }
+        }
+        Ok(releases)
+    }
+}
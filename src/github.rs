@@ -0,0 +1,436 @@
+use crate::config::Config;
+use crate::repository::Repository;
+use crate::source::{self, Asset, Release};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use http::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, ETAG, IF_NONE_MATCH};
+use octocrab::models::ArtifactId;
+use octocrab::params::actions::ArchiveFormat;
+use octocrab::Octocrab;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+// Keyring entry the token is looked up under.
+const KEYRING_SERVICE: &str = "originalife-manager";
+const KEYRING_ACCOUNT: &str = "github";
+// Longest rate-limit reset the manager waits out instead of giving up.
+const MAX_WAIT: Duration = Duration::from_secs(10 * 60);
+
+// One API response as stored in `cache_dir`.
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,
+    fetched_at: String,
+    body: String,
+}
+
+impl CachedResponse {
+    fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_str(&self.body).context("Failed to parse GitHub response")
+    }
+}
+
+// An Actions artifact and the run that built it. octocrab's
+// `WorkflowListArtifact` leaves out `workflow_run`.
+#[derive(Deserialize)]
+pub struct Artifact {
+    pub id: ArtifactId,
+    pub name: String,
+    pub expired: bool,
+    pub created_at: DateTime<Utc>,
+    pub workflow_run: Option<WorkflowRun>,
+}
+
+#[derive(Deserialize)]
+pub struct WorkflowRun {
+    pub head_branch: Option<String>,
+    pub head_repository_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct RepositoryInfo {
+    pub id: u64,
+    pub default_branch: String,
+}
+
+// GitHub API access for one repository. Responses are kept in `cache_dir` and
+// revalidated with their ETag, so unchanged data costs no rate limit when
+// authenticated and a rate-limited, offline or failing run can fall back to
+// what the last run saw.
+pub struct GitHub {
+    octocrab: Octocrab,
+    repository: Repository,
+    cache_dir: PathBuf,
+    token: Option<String>,
+}
+
+impl GitHub {
+    pub fn new(repository: &Repository, config: &Config) -> Result<Self> {
+        if repository.is_nested() {
+            bail!(
+                "GitHub repositories are 'owner/name', '{}' is nested",
+                repository
+            );
+        }
+        let token = token(config.github_token.as_deref());
+        let builder = match &token {
+            Some(token) => Octocrab::builder().personal_token(token.clone()),
+            None => Octocrab::builder(),
+        };
+        Ok(Self {
+            octocrab: builder.build().context("Failed to build Octocrab client")?,
+            repository: repository.clone(),
+            cache_dir: config.data_dir.join("cache").join("github"),
+            token,
+        })
+    }
+
+    pub fn authenticated(&self) -> bool {
+        self.token.is_some()
+    }
+
+    pub async fn latest_release(&self) -> Result<Release> {
+        let release: octocrab::models::repos::Release = self
+            .get(&format!("/repos/{}/releases/latest", self.repository))
+            .await?;
+        Ok(self.convert(release))
+    }
+
+    pub async fn release(&self, tag: &str) -> Result<Release> {
+        let release: octocrab::models::repos::Release = self
+            .get(&format!(
+                "/repos/{}/releases/tags/{}",
+                self.repository,
+                source::path_segment(tag)
+            ))
+            .await?;
+        Ok(self.convert(release))
+    }
+
+    // Newest first, `pages` pages of 100 at most.
+    pub async fn releases(&self, pages: usize) -> Result<Vec<Release>> {
+        let mut releases = Vec::new();
+        for page in 1..=pages {
+            let batch: Vec<octocrab::models::repos::Release> = self
+                .get(&format!(
+                    "/repos/{}/releases?per_page=100&page={}",
+                    self.repository, page
+                ))
+                .await?;
+            let last = batch.len() < 100;
+            releases.extend(batch.into_iter().map(|r| self.convert(r)));
+            if last {
+                break;
+            }
+        }
+        Ok(releases)
+    }
+
+    pub async fn repository_info(&self) -> Result<RepositoryInfo> {
+        self.get(&format!("/repos/{}", self.repository)).await
+    }
+
+    // Newest first.
+    pub async fn artifacts(&self) -> Result<Vec<Artifact>> {
+        #[derive(Deserialize)]
+        struct ArtifactList {
+            artifacts: Vec<Artifact>,
+        }
+
+        let list: ArtifactList = self
+            .get(&format!(
+                "/repos/{}/actions/artifacts?per_page=100",
+                self.repository
+            ))
+            .await?;
+        Ok(list.artifacts)
+    }
+
+    pub async fn download_artifact(&self, artifact: &Artifact) -> Result<Vec<u8>> {
+        let bytes = self
+            .octocrab
+            .actions()
+            .download_artifact(
+                &self.repository.owner,
+                &self.repository.name,
+                artifact.id,
+                ArchiveFormat::Zip,
+            )
+            .await
+            .context("Failed to download workflow artifact")?;
+        Ok(bytes.to_vec())
+    }
+
+    async fn get<T: DeserializeOwned>(&self, route: &str) -> Result<T> {
+        let cache_path = self.cache_dir.join(cache_name(route));
+        let cached = CachedResponse::load(&cache_path);
+        let mut headers = HeaderMap::new();
+        if let Some(etag) = cached.as_ref().and_then(|c| c.etag.as_deref()) {
+            if let Ok(value) = HeaderValue::from_str(etag) {
+                headers.insert(IF_NONE_MATCH, value);
+            }
+        }
+
+        let mut waited = false;
+        loop {
+            let response = match self
+                .octocrab
+                ._get_with_headers(route, Some(headers.clone()))
+                .await
+            {
+                Ok(response) => response,
+                Err(error) => match &cached {
+                    Some(cached) => {
+                        println!(
+                            "Could not reach GitHub, using release information cached at {}.",
+                            cached.fetched_at
+                        );
+                        return cached.parse();
+                    }
+                    None => return Err(error).context("Failed to reach GitHub"),
+                },
+            };
+            let status = response.status();
+            let header = |name: &str| -> Option<u64> {
+                response.headers().get(name)?.to_str().ok()?.parse().ok()
+            };
+            let remaining = header("x-ratelimit-remaining");
+            let limited = (status.as_u16() == 403 || status.as_u16() == 429)
+                && (remaining == Some(0) || header("retry-after").is_some());
+
+            if limited {
+                let wait = header("retry-after").unwrap_or_else(|| {
+                    let reset = header("x-ratelimit-reset").unwrap_or_default() as i64;
+                    (reset - chrono::Utc::now().timestamp()).max(1) as u64
+                });
+                let wait = Duration::from_secs(wait);
+                if let Some(cached) = &cached {
+                    println!(
+                        "GitHub's rate limit is reached (resets in {} min), using release information cached at {}.",
+                        wait.as_secs().div_ceil(60),
+                        cached.fetched_at
+                    );
+                    return cached.parse();
+                }
+                if !waited && wait <= MAX_WAIT {
+                    println!(
+                        "GitHub's rate limit is reached, waiting {}s for it to reset...",
+                        wait.as_secs()
+                    );
+                    tokio::time::sleep(wait).await;
+                    waited = true;
+                    continue;
+                }
+                bail!(
+                    "GitHub's rate limit is reached and resets in {} min. Set a token (GITHUB_TOKEN, `github_token` or the keyring) for a higher limit",
+                    wait.as_secs().div_ceil(60)
+                );
+            }
+
+            // Not modified since the cached copy. GitHub only leaves this out of
+            // the rate limit for authenticated requests.
+            if status.as_u16() == 304 {
+                if let Some(cached) = &cached {
+                    return cached.parse();
+                }
+            }
+            // An outage on GitHub's side is no reason to fail while there's a copy.
+            if status.is_server_error() {
+                if let Some(cached) = &cached {
+                    println!(
+                        "GitHub returned {}, using release information cached at {}.",
+                        status.as_u16(),
+                        cached.fetched_at
+                    );
+                    return cached.parse();
+                }
+            }
+
+            let etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let body = self
+                .octocrab
+                .body_to_string(response)
+                .await
+                .context("Failed to read GitHub response")?;
+            match status.as_u16() {
+                200..=299 => {}
+                401 => bail!("GitHub rejected the token, check GITHUB_TOKEN or `github_token`"),
+                code => {
+                    let message = serde_json::from_str::<serde_json::Value>(&body)
+                        .ok()
+                        .and_then(|v| v["message"].as_str().map(str::to_string))
+                        .unwrap_or(body);
+                    bail!("GitHub returned {}: {}", code, message);
+                }
+            }
+            if remaining.is_some_and(|r| r < 5) {
+                println!(
+                    "Warning: only {} GitHub API requests left this hour",
+                    remaining.unwrap_or_default()
+                );
+            }
+
+            let fresh = CachedResponse {
+                etag,
+                fetched_at: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+                body,
+            };
+            // Caching is best effort, a read-only data dir shouldn't break updates.
+            if fs::create_dir_all(&self.cache_dir).is_ok() {
+                if let Ok(contents) = serde_json::to_string(&fresh) {
+                    let _ = fs::write(&cache_path, contents);
+                }
+            }
+            return fresh.parse();
+        }
+    }
+
+    // With a token assets are fetched through the API, the browser download
+    // URLs of private repositories don't accept one.
+    fn convert(&self, release: octocrab::models::repos::Release) -> Release {
+        let headers = |token: &str| {
+            let mut headers = HeaderMap::new();
+            if let Ok(value) = HeaderValue::from_str(&format!("Bearer {token}")) {
+                headers.insert(AUTHORIZATION, value);
+            }
+            headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+            headers
+        };
+        Release {
+            tag: release.tag_name,
+            published_at: release.published_at,
+            prerelease: release.prerelease,
+            draft: release.draft,
+            assets: release
+                .assets
+                .into_iter()
+                .map(|a| match &self.token {
+                    Some(token) => Asset {
+                        name: a.name,
+                        size: Some(a.size as u64),
+                        url: a.url.to_string(),
+                        headers: headers(token),
+                    },
+                    None => Asset {
+                        name: a.name,
+                        size: Some(a.size as u64),
+                        url: a.browser_download_url.to_string(),
+                        headers: HeaderMap::new(),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+fn cache_name(route: &str) -> String {
+    let name: String = route
+        .trim_start_matches('/')
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect();
+    format!("{name}.json")
+}
+
+// `GITHUB_TOKEN` wins over the config, which wins over the OS keyring.
+pub fn token(configured: Option<&str>) -> Option<String> {
+    // An empty `GITHUB_TOKEN` must not hide the config or the keyring.
+    let present = |token: String| Some(token.trim().to_string()).filter(|t| !t.is_empty());
+    env::var("GITHUB_TOKEN")
+        .ok()
+        .and_then(present)
+        .or_else(|| configured.map(str::to_string).and_then(present))
+        .or_else(|| keyring_token().and_then(present))
+}
+
+// Uses the platform's keyring tool, stored with e.g.
+// `secret-tool store --label=originalife service originalife-manager account github`,
+// or the Windows Credential Manager.
+fn keyring_token() -> Option<String> {
+    let mut command = match env::consts::OS {
+        "linux" => {
+            let mut command = Command::new("secret-tool");
+            command.args([
+                "lookup",
+                "service",
+                KEYRING_SERVICE,
+                "account",
+                KEYRING_ACCOUNT,
+            ]);
+            command
+        }
+        "macos" => {
+            let mut command = Command::new("security");
+            command.args([
+                "find-generic-password",
+                "-s",
+                KEYRING_SERVICE,
+                "-a",
+                KEYRING_ACCOUNT,
+                "-w",
+            ]);
+            command
+        }
+        "windows" => return windows_credential(),
+        _ => return None,
+    };
+    let output = command.stderr(Stdio::null()).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+// A generic credential stored with
+// `cmdkey /generic:originalife-manager /user:github /pass:<token>`.
+#[cfg(windows)]
+fn windows_credential() -> Option<String> {
+    use windows_sys::Win32::Security::Credentials::{
+        CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC,
+    };
+
+    let target: Vec<u16> = KEYRING_SERVICE.encode_utf16().chain([0]).collect();
+    let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+    // SAFETY: `target` is NUL-terminated and `credential` is only read after
+    // a successful call, then released with `CredFree`.
+    unsafe {
+        if CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) == 0 {
+            return None;
+        }
+        let blob = std::slice::from_raw_parts(
+            (*credential).CredentialBlob,
+            (*credential).CredentialBlobSize as usize,
+        );
+        // cmdkey stores the password as UTF-16.
+        let secret: Vec<u16> = blob
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        let token = String::from_utf16(&secret).ok();
+        CredFree(credential as *const _);
+        token
+    }
+}
+
+#[cfg(not(windows))]
+fn windows_credential() -> Option<String> {
+    None
+}
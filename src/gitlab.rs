@@ -0,0 +1,115 @@
+use crate::repository::Repository;
+use crate::source::{self, Asset, Release};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+const PAGE_SIZE: usize = 100;
+
+// Releases of a GitLab project; assets are the release's links.
+pub struct GitLab {
+    client: reqwest::Client,
+    api: String,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    released_at: Option<DateTime<Utc>>,
+    // Releases dated in the future, the closest GitLab has to a pre-release.
+    #[serde(default)]
+    upcoming_release: bool,
+    assets: GitLabAssets,
+}
+
+#[derive(Deserialize)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+impl GitLab {
+    pub fn new(url: &str, repository: &Repository, token: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            // The API takes the whole project path, subgroups included, as one segment.
+            api: format!(
+                "{}/api/v4/projects/{}",
+                url.trim_end_matches('/'),
+                source::path_segment(&repository.to_string())
+            ),
+            token,
+        }
+    }
+
+    fn convert(&self, release: GitLabRelease) -> Release {
+        Release {
+            tag: release.tag_name,
+            published_at: release.released_at,
+            prerelease: release.upcoming_release,
+            draft: false,
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| {
+                    let url = link.direct_asset_url.unwrap_or(link.url);
+                    Asset {
+                        headers: source::token_headers(
+                            &url,
+                            &self.api,
+                            "PRIVATE-TOKEN",
+                            self.token.clone(),
+                        ),
+                        name: link.name,
+                        size: None,
+                        url,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let mut request = self.client.get(format!("{}{}", self.api, path));
+        if let Some(token) = &self.token {
+            request = request.header("PRIVATE-TOKEN", token);
+        }
+        source::get_json(request).await
+    }
+
+    pub async fn latest_release(&self) -> Result<Release> {
+        let release: GitLabRelease = self.get("/releases/permalink/latest").await?;
+        Ok(self.convert(release))
+    }
+
+    pub async fn release(&self, tag: &str) -> Result<Release> {
+        let release: GitLabRelease = self
+            .get(&format!("/releases/{}", source::path_segment(tag)))
+            .await?;
+        Ok(self.convert(release))
+    }
+
+    pub async fn releases(&self, pages: usize) -> Result<Vec<Release>> {
+        let mut releases = Vec::new();
+        for page in 1..=pages {
+            let batch: Vec<GitLabRelease> = self
+                .get(&format!("/releases?per_page={PAGE_SIZE}&page={page}"))
+                .await?;
+            let last = batch.len() < PAGE_SIZE;
+            releases.extend(batch.into_iter().map(|r| self.convert(r)));
+            if last {
+                break;
+            }
+        }
+        Ok(releases)
+    }
+}
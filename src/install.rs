@@ -0,0 +1,662 @@
+use crate::extract::{self, Entry};
+use crate::manifest::{self, Manifest, MANIFEST_FILE};
+use crate::preserve::PreservePolicy;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlannedWrite {
+    path: PathBuf,
+    existed: bool,
+}
+
+// Written next to the instance before anything is touched, so an interrupted
+// install can be rolled back on the next run.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    writes: Vec<PlannedWrite>,
+    deletes: Vec<PathBuf>,
+}
+
+struct Paths {
+    target: PathBuf,
+    staging: PathBuf,
+    backup: PathBuf,
+    journal: PathBuf,
+}
+
+impl Paths {
+    fn new(target: &Path) -> Result<Self> {
+        let parent = target
+            .parent()
+            .context("Target directory has no parent directory")?;
+        let name = target
+            .file_name()
+            .context("Target directory has no name")?
+            .to_string_lossy();
+        Ok(Self {
+            target: target.to_path_buf(),
+            staging: parent.join(format!(".{name}.staging")),
+            backup: parent.join(format!(".{name}.backup")),
+            journal: parent.join(format!(".{name}.journal.json")),
+        })
+    }
+
+    // Removing the journal is the commit point: once it's gone nothing will
+    // roll back, so it has to go before the backup it refers to.
+    fn commit(&self) -> Result<()> {
+        if self.journal.exists() {
+            fs::remove_file(&self.journal).context("Failed to remove install journal")?;
+        }
+        Ok(())
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.commit()?;
+        for dir in [&self.staging, &self.backup] {
+            if dir.exists() {
+                fs::remove_dir_all(dir)
+                    .with_context(|| format!("Failed to remove {}", dir.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Removes staging, backup and journal files left next to the instance.
+pub fn discard_leftovers(target: &Path) -> Result<()> {
+    Paths::new(target)?.cleanup()
+}
+
+// Rolls back an install that was interrupted before it could finish.
+pub fn recover(target: &Path) -> Result<()> {
+    let paths = Paths::new(target)?;
+    if !paths.journal.exists() {
+        return Ok(());
+    }
+
+    println!("Found an interrupted update, restoring the previous instance...");
+    let contents = fs::read_to_string(&paths.journal).context("Failed to read install journal")?;
+    let journal: Journal =
+        serde_json::from_str(&contents).context("Failed to parse install journal")?;
+    rollback(&paths, &journal)
+}
+
+struct Prepared<R> {
+    archive: ZipArchive<R>,
+    entries: Vec<Entry>,
+    manifest: Manifest,
+    // What gets recorded as installed, when the archive brings its own.
+    recorded: Option<Manifest>,
+    journal: Journal,
+    incremental: bool,
+}
+
+// With `incremental` set and a manifest from a previous install present, only
+// files that the release added, changed or removed are touched. Otherwise the
+// instance is made to match the archive, keeping preserved player data.
+fn prepare<R: Read + Seek>(
+    reader: R,
+    target: &Path,
+    policy: &PreservePolicy,
+    incremental: bool,
+) -> Result<Prepared<R>> {
+    let mut archive = ZipArchive::new(reader).context("Failed to create ZIP archive")?;
+
+    let entries = extract::scan(&mut archive).context("Failed to read ZIP archive")?;
+    let manifest =
+        Manifest::from_entries(&mut archive, &entries).context("Failed to read ZIP archive")?;
+    if manifest.files.is_empty() {
+        bail!("The release archive contains no files");
+    }
+    let recorded = Manifest::embedded(&mut archive, &entries)?;
+    let previous = match incremental {
+        true => Manifest::load(target)?,
+        false => None,
+    };
+    let journal = match &previous {
+        Some(previous) => plan_incremental(&manifest, previous, target, policy),
+        None => plan(&manifest, &existing_files(target)?, target, policy),
+    };
+
+    Ok(Prepared {
+        archive,
+        entries,
+        manifest,
+        recorded,
+        journal,
+        incremental: previous.is_some(),
+    })
+}
+
+pub fn install(
+    archive_path: &Path,
+    target: &Path,
+    policy: &PreservePolicy,
+    incremental: bool,
+) -> Result<()> {
+    recover(target)?;
+    let paths = Paths::new(target)?;
+    paths.cleanup()?;
+
+    let file = fs::File::open(archive_path).context("Failed to open temporary file")?;
+    let Prepared {
+        mut archive,
+        entries,
+        manifest,
+        recorded,
+        mut journal,
+        incremental,
+    } = prepare(file, target, policy, incremental)?;
+    print_summary(&journal, incremental);
+
+    fs::create_dir_all(&paths.staging).context("Failed to create staging directory")?;
+    let staged = stage(&mut archive, &entries, &journal, &paths)
+        .context("Failed to extract ZIP archive")
+        .and_then(|_| stage_manifest(recorded.as_ref().unwrap_or(&manifest), &mut journal, &paths))
+        .and_then(|_| validate(&journal, &paths));
+    if let Err(e) = staged {
+        paths.cleanup()?;
+        return Err(e);
+    }
+
+    fs::write(
+        &paths.journal,
+        serde_json::to_string(&journal).context("Failed to serialize install journal")?,
+    )
+    .context("Failed to write install journal")?;
+
+    if let Err(e) = apply(&journal, &paths) {
+        rollback(&paths, &journal).context("Failed to roll back the update")?;
+        return Err(e.context("Failed to apply update, the previous instance was restored"));
+    }
+    paths.commit()?;
+
+    prune_empty_parents(target, &journal.deletes)?;
+    paths.cleanup()
+}
+
+// Whether `install` would write or delete anything in `target`.
+pub fn has_changes(
+    archive_path: &Path,
+    target: &Path,
+    policy: &PreservePolicy,
+    incremental: bool,
+) -> Result<bool> {
+    let file = fs::File::open(archive_path).context("Failed to open temporary file")?;
+    let journal = prepare(file, target, policy, incremental)?.journal;
+    Ok(!journal.writes.is_empty() || !journal.deletes.is_empty())
+}
+
+// Prints what `install` would do without writing anything.
+pub fn preview<R: Read + Seek>(
+    reader: R,
+    target: &Path,
+    policy: &PreservePolicy,
+    incremental: bool,
+) -> Result<()> {
+    println!("Dry run, nothing in {} will be changed.", target.display());
+    if Paths::new(target)?.journal.exists() {
+        println!("An interrupted update would be rolled back first.");
+    }
+
+    let prepared = prepare(reader, target, policy, incremental)?;
+    let journal = &prepared.journal;
+    let (replaced, added): (Vec<_>, Vec<_>) = journal.writes.iter().partition(|w| w.existed);
+
+    let touched: HashSet<&PathBuf> = journal
+        .writes
+        .iter()
+        .map(|w| &w.path)
+        .chain(&journal.deletes)
+        .collect();
+    let mut preserved: BTreeMap<String, usize> = BTreeMap::new();
+    let mut untracked = Vec::new();
+    let mut unchanged = 0;
+    for file in existing_files(target)? {
+        if touched.contains(&file) {
+            continue;
+        }
+        let in_pack = prepared.manifest.get(&file).is_some();
+        if policy.is_preserved(&file, in_pack) {
+            *preserved.entry(top_level(&file)).or_default() += 1;
+        } else if in_pack {
+            unchanged += 1;
+        } else {
+            untracked.push(file);
+        }
+    }
+
+    println!("Add ({}):", added.len());
+    for write in &added {
+        println!("  + {}", write.path.display());
+    }
+    println!("Replace ({}):", replaced.len());
+    for write in &replaced {
+        println!("  ~ {}", write.path.display());
+    }
+    println!("Delete ({}):", journal.deletes.len());
+    for delete in &journal.deletes {
+        println!("  - {}", delete.display());
+    }
+    println!("Preserve ({}):", preserved.values().sum::<usize>());
+    for (path, count) in &preserved {
+        match count {
+            1 => println!("  = {}", path),
+            _ => println!("  = {} ({} files)", path, count),
+        }
+    }
+    if prepared.incremental {
+        println!("Keep, not part of the pack ({}):", untracked.len());
+        for file in &untracked {
+            println!("  = {}", file.display());
+        }
+    }
+    println!("Unchanged: {} files", unchanged);
+    Ok(())
+}
+
+// Groups preserved files by their top-level directory inside the game directory.
+fn top_level(path: &Path) -> String {
+    let parts: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    let skip = match parts.first().map(String::as_str) {
+        Some(".minecraft") | Some("minecraft") if parts.len() > 2 => 2,
+        _ => 1,
+    };
+    match parts.len() > skip {
+        true => format!("{}/", parts[..skip].join("/")),
+        false => parts.join("/"),
+    }
+}
+
+pub fn existing_files(target: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if target.exists() {
+        collect_files(target, target, &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relative = path.strip_prefix(root)?;
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if relative != Path::new(MANIFEST_FILE) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn plan(
+    manifest: &Manifest,
+    existing: &[PathBuf],
+    target: &Path,
+    policy: &PreservePolicy,
+) -> Journal {
+    let mut journal = Journal::default();
+
+    for entry in manifest.paths() {
+        let existed = target.join(&entry).exists();
+        // Keep the player's copy; the pack's version is only a first-install default.
+        if existed && policy.is_preserved(&entry, true) {
+            continue;
+        }
+        journal.writes.push(PlannedWrite {
+            path: entry,
+            existed,
+        });
+    }
+
+    for file in existing {
+        if manifest.get(file).is_none() && !policy.is_preserved(file, false) {
+            journal.deletes.push(file.clone());
+        }
+    }
+
+    journal
+}
+
+// Files that are unchanged between the two releases are left alone, even if
+// the player edited them since.
+fn plan_incremental(
+    manifest: &Manifest,
+    previous: &Manifest,
+    target: &Path,
+    policy: &PreservePolicy,
+) -> Journal {
+    let mut journal = Journal::default();
+
+    for (entry, hash) in manifest.paths().zip(manifest.files.values()) {
+        let existed = target.join(&entry).exists();
+        if existed && policy.is_preserved(&entry, true) {
+            continue;
+        }
+        if existed && previous.get(&entry) == Some(hash) {
+            continue;
+        }
+        journal.writes.push(PlannedWrite {
+            path: entry,
+            existed,
+        });
+    }
+
+    for entry in previous.paths() {
+        if manifest.get(&entry).is_none()
+            && target.join(&entry).exists()
+            && !policy.is_preserved(&entry, true)
+        {
+            journal.deletes.push(entry);
+        }
+    }
+
+    journal
+}
+
+fn print_summary(journal: &Journal, incremental: bool) {
+    let added = journal.writes.iter().filter(|w| !w.existed).count();
+    let changed = journal.writes.len() - added;
+    println!(
+        "{} added, {} changed, {} removed",
+        added,
+        changed,
+        journal.deletes.len()
+    );
+
+    if incremental {
+        for write in &journal.writes {
+            let marker = if write.existed { "~" } else { "+" };
+            println!("  {} {}", marker, write.path.display());
+        }
+        for delete in &journal.deletes {
+            println!("  - {}", delete.display());
+        }
+    }
+}
+
+fn stage<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    entries: &[Entry],
+    journal: &Journal,
+    paths: &Paths,
+) -> Result<()> {
+    let wanted: HashSet<&PathBuf> = journal.writes.iter().map(|w| &w.path).collect();
+    for entry in entries {
+        if !wanted.contains(&entry.path) || manifest::entry_name(&entry.path) == MANIFEST_FILE {
+            continue;
+        }
+        extract::write_entry(archive, entry, &paths.staging.join(&entry.path))?;
+    }
+    Ok(())
+}
+
+fn stage_manifest(manifest: &Manifest, journal: &mut Journal, paths: &Paths) -> Result<()> {
+    let contents =
+        serde_json::to_string_pretty(manifest).context("Failed to serialize manifest")?;
+    fs::write(paths.staging.join(MANIFEST_FILE), contents).context("Failed to write manifest")?;
+    journal.writes.push(PlannedWrite {
+        path: PathBuf::from(MANIFEST_FILE),
+        existed: paths.target.join(MANIFEST_FILE).exists(),
+    });
+    Ok(())
+}
+
+fn validate(journal: &Journal, paths: &Paths) -> Result<()> {
+    for write in &journal.writes {
+        if !paths.staging.join(&write.path).is_file() {
+            bail!("Staged file is missing: {}", write.path.display());
+        }
+    }
+    Ok(())
+}
+
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+}
+
+fn apply(journal: &Journal, paths: &Paths) -> Result<()> {
+    fs::create_dir_all(&paths.target).context("Failed to create target directory")?;
+
+    for delete in &journal.deletes {
+        move_file(&paths.target.join(delete), &paths.backup.join(delete))?;
+    }
+    for write in &journal.writes {
+        let destination = paths.target.join(&write.path);
+        if write.existed {
+            move_file(&destination, &paths.backup.join(&write.path))?;
+        }
+        move_file(&paths.staging.join(&write.path), &destination)?;
+    }
+    Ok(())
+}
+
+fn rollback(paths: &Paths, journal: &Journal) -> Result<()> {
+    for write in &journal.writes {
+        let destination = paths.target.join(&write.path);
+        let backup = paths.backup.join(&write.path);
+        if write.existed && !backup.exists() {
+            // Never moved out, still the original.
+            continue;
+        }
+        if destination.exists() {
+            fs::remove_file(&destination)?;
+        }
+        if backup.exists() {
+            move_file(&backup, &destination)?;
+        }
+    }
+    for delete in &journal.deletes {
+        let backup = paths.backup.join(delete);
+        if backup.exists() {
+            move_file(&backup, &paths.target.join(delete))?;
+        }
+    }
+
+    let added: Vec<PathBuf> = journal
+        .writes
+        .iter()
+        .filter(|w| !w.existed)
+        .map(|w| w.path.clone())
+        .collect();
+    prune_empty_parents(&paths.target, &added)?;
+    paths.cleanup()
+}
+
+fn prune_empty_parents(root: &Path, removed: &[PathBuf]) -> Result<()> {
+    for path in removed {
+        let mut dir = path.parent();
+        while let Some(relative) = dir.filter(|d| !d.as_os_str().is_empty()) {
+            let full = root.join(relative);
+            let is_empty = fs::read_dir(&full)
+                .map(|mut entries| entries.next().is_none())
+                .unwrap_or(false);
+            if !is_empty {
+                break;
+            }
+            fs::remove_dir(&full)?;
+            dir = relative.parent();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher::Launcher;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("install-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn manifest(files: &[(&str, &str)]) -> Manifest {
+        Manifest {
+            files: files
+                .iter()
+                .map(|(path, hash)| (path.to_string(), hash.to_string()))
+                .collect(),
+        }
+    }
+
+    fn written(journal: &Journal) -> Vec<(&str, bool)> {
+        journal
+            .writes
+            .iter()
+            .map(|w| (w.path.to_str().unwrap(), w.existed))
+            .collect()
+    }
+
+    fn deleted(journal: &Journal) -> Vec<&str> {
+        journal
+            .deletes
+            .iter()
+            .map(|d| d.to_str().unwrap())
+            .collect()
+    }
+
+    fn policy() -> PreservePolicy {
+        PreservePolicy::new(&[], Launcher::Modrinth)
+    }
+
+    #[test]
+    fn incremental_plan_touches_only_what_changed() {
+        let target = temp_dir("incremental");
+        for file in [
+            "mods/same.jar",
+            "mods/changed.jar",
+            "mods/dropped.jar",
+            "options.txt",
+        ] {
+            write(&target, file, "old");
+        }
+        write(&target, "mods/mine.jar", "added by the player");
+        let previous = manifest(&[
+            ("mods/same.jar", "1"),
+            ("mods/changed.jar", "2"),
+            ("mods/dropped.jar", "3"),
+            ("options.txt", "4"),
+        ]);
+        let next = manifest(&[
+            ("mods/same.jar", "1"),
+            ("mods/changed.jar", "22"),
+            ("mods/new.jar", "5"),
+            ("options.txt", "44"),
+        ]);
+
+        let journal = plan_incremental(&next, &previous, &target, &policy());
+        let _ = fs::remove_dir_all(&target);
+        assert_eq!(
+            written(&journal),
+            [("mods/changed.jar", true), ("mods/new.jar", false)]
+        );
+        assert_eq!(deleted(&journal), ["mods/dropped.jar"]);
+    }
+
+    #[test]
+    fn incremental_plan_keeps_preserved_files() {
+        let target = temp_dir("incremental-preserved");
+        write(&target, "saves/world/level.dat", "player");
+        write(&target, "options.txt", "player");
+        let previous = manifest(&[("saves/world/level.dat", "1"), ("options.txt", "2")]);
+        let next = manifest(&[("options.txt", "3")]);
+
+        let journal = plan_incremental(&next, &previous, &target, &policy());
+        let _ = fs::remove_dir_all(&target);
+        assert!(journal.writes.is_empty());
+        assert!(journal.deletes.is_empty());
+    }
+
+    #[test]
+    fn full_plan_matches_the_archive() {
+        let target = temp_dir("full");
+        write(&target, "mods/kept.jar", "old");
+        write(&target, "mods/stray.jar", "old");
+        write(&target, "saves/world/level.dat", "player");
+        write(&target, "options.txt", "player");
+        let existing = existing_files(&target).unwrap();
+        let next = manifest(&[
+            ("mods/kept.jar", "1"),
+            ("mods/new.jar", "2"),
+            ("options.txt", "3"),
+        ]);
+
+        let journal = plan(&next, &existing, &target, &policy());
+        let _ = fs::remove_dir_all(&target);
+        assert_eq!(
+            written(&journal),
+            [("mods/kept.jar", true), ("mods/new.jar", false)]
+        );
+        assert_eq!(deleted(&journal), ["mods/stray.jar"]);
+    }
+
+    #[test]
+    fn rollback_undoes_a_partial_apply() {
+        let root = temp_dir("rollback");
+        let target = root.join("instance");
+        write(&target, "mods/a.jar", "old a");
+        write(&target, "mods/gone.jar", "old gone");
+        let paths = Paths::new(&target).unwrap();
+        write(&paths.staging, "mods/a.jar", "new a");
+        write(&paths.staging, "mods/added.jar", "new added");
+        // The last staged file is missing, so `apply` fails halfway.
+        let journal = Journal {
+            writes: vec![
+                PlannedWrite {
+                    path: PathBuf::from("mods/a.jar"),
+                    existed: true,
+                },
+                PlannedWrite {
+                    path: PathBuf::from("mods/added.jar"),
+                    existed: false,
+                },
+                PlannedWrite {
+                    path: PathBuf::from("config/missing.toml"),
+                    existed: false,
+                },
+            ],
+            deletes: vec![PathBuf::from("mods/gone.jar")],
+        };
+        fs::write(&paths.journal, serde_json::to_string(&journal).unwrap()).unwrap();
+
+        assert!(apply(&journal, &paths).is_err());
+        assert!(!target.join("mods/gone.jar").exists());
+        rollback(&paths, &journal).unwrap();
+
+        let read = |path: &str| fs::read_to_string(target.join(path)).ok();
+        let (a, gone, added) = (
+            read("mods/a.jar"),
+            read("mods/gone.jar"),
+            read("mods/added.jar"),
+        );
+        let leftovers = paths.journal.exists() || paths.backup.exists() || paths.staging.exists();
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(a.as_deref(), Some("old a"));
+        assert_eq!(gone.as_deref(), Some("old gone"));
+        assert_eq!(added, None);
+        assert!(!leftovers);
+    }
+}
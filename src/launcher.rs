@@ -0,0 +1,574 @@
+use crate::atlauncher;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Launcher {
+    Modrinth,
+    CurseForge,
+    Prism,
+    MultiMc,
+    Atlauncher,
+    Gdlauncher,
+    Vanilla,
+    // A dedicated server directory rather than a launcher, only picked with `--launcher server`.
+    Server,
+}
+
+impl Launcher {
+    pub const ALL: [Self; 7] = [
+        Self::Modrinth,
+        Self::CurseForge,
+        Self::Prism,
+        Self::MultiMc,
+        Self::Atlauncher,
+        Self::Gdlauncher,
+        Self::Vanilla,
+    ];
+
+    // MultiMC reads Prism's instance format, the rest take a plain game directory.
+    pub fn artifact_name(self) -> &'static str {
+        match self {
+            Self::CurseForge => "updated-pack-curseforge.zip",
+            Self::Prism | Self::MultiMc => "updated-pack-prism.zip",
+            Self::Modrinth | Self::Atlauncher | Self::Gdlauncher | Self::Vanilla | Self::Server => {
+                "updated-pack-modrinth.zip"
+            }
+        }
+    }
+
+    // Data directories the launcher may use on `env`'s platform, most likely first.
+    pub fn data_dirs(self, env: &Environment) -> Vec<PathBuf> {
+        let home = env.home();
+        let mut dirs = Vec::new();
+        match (self, env.platform) {
+            (Self::Modrinth, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join("ModrinthApp"));
+                    dirs.push(appdata.join("com.modrinth.theseus"));
+                }
+            }
+            (Self::Modrinth, Platform::MacOs) => {
+                if let Some(support) = home.map(|h| h.join("Library/Application Support")) {
+                    dirs.push(support.join("ModrinthApp"));
+                    dirs.push(support.join("com.modrinth.theseus"));
+                }
+            }
+            (Self::Modrinth, Platform::Linux) => {
+                if let Some(data) = env.data_home() {
+                    dirs.push(data.join("ModrinthApp"));
+                    dirs.push(data.join("com.modrinth.theseus"));
+                }
+                if let Some(home) = home {
+                    dirs.push(home.join(".var/app/com.modrinth.ModrinthApp/data/ModrinthApp"));
+                }
+            }
+            (Self::CurseForge, Platform::Windows) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("curseforge").join("minecraft"));
+                }
+            }
+            (Self::CurseForge, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Documents/curseforge/minecraft"));
+                }
+            }
+            (Self::CurseForge, Platform::Linux) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("curseforge/minecraft"));
+                }
+            }
+            (Self::Prism, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join("PrismLauncher"));
+                }
+            }
+            (Self::Prism, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Library/Application Support/PrismLauncher"));
+                }
+            }
+            (Self::Prism, Platform::Linux) => {
+                if let Some(data) = env.data_home() {
+                    dirs.push(data.join("PrismLauncher"));
+                }
+                if let Some(home) = home {
+                    dirs.push(
+                        home.join(".var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher"),
+                    );
+                }
+            }
+            // MultiMC is portable, these are only the usual places people unpack it.
+            (Self::MultiMc, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join("MultiMC"));
+                }
+                if let Some(home) = home {
+                    dirs.push(home.join("MultiMC"));
+                }
+            }
+            (Self::MultiMc, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Library/Application Support/MultiMC"));
+                }
+            }
+            (Self::MultiMc, Platform::Linux) => {
+                if let Some(data) = env.data_home() {
+                    dirs.push(data.join("multimc"));
+                }
+                if let Some(home) = home {
+                    dirs.push(home.join("MultiMC"));
+                }
+            }
+            (Self::Atlauncher, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join("ATLauncher"));
+                }
+            }
+            (Self::Atlauncher, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Library/Application Support/ATLauncher"));
+                }
+            }
+            (Self::Atlauncher, Platform::Linux) => {
+                if let Some(data) = env.data_home() {
+                    dirs.push(data.join("atlauncher"));
+                    dirs.push(data.join("ATLauncher"));
+                }
+                if let Some(home) = home {
+                    dirs.push(home.join(".var/app/com.atlauncher.ATLauncher/data/atlauncher"));
+                }
+            }
+            (Self::Gdlauncher, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join("gdlauncher_next"));
+                }
+            }
+            (Self::Gdlauncher, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Library/Application Support/gdlauncher_next"));
+                }
+            }
+            (Self::Gdlauncher, Platform::Linux) => {
+                if let Some(config) = env.config_home() {
+                    dirs.push(config.join("gdlauncher_next"));
+                }
+            }
+            (Self::Vanilla, Platform::Windows) => {
+                if let Some(appdata) = env.path("APPDATA") {
+                    dirs.push(appdata.join(".minecraft"));
+                }
+            }
+            (Self::Vanilla, Platform::MacOs) => {
+                if let Some(home) = home {
+                    dirs.push(home.join("Library/Application Support/minecraft"));
+                }
+            }
+            (Self::Vanilla, Platform::Linux) => {
+                if let Some(home) = home {
+                    dirs.push(home.join(".minecraft"));
+                    dirs.push(home.join(".var/app/com.mojang.Minecraft/.minecraft"));
+                }
+            }
+            (Self::Server, _) => {}
+        }
+        dirs
+    }
+
+    // Where instances live inside a data directory.
+    pub fn instances_dir(self, data_dir: &Path) -> PathBuf {
+        match self {
+            Self::Modrinth => data_dir.join("profiles"),
+            Self::CurseForge => data_dir.join("Instances"),
+            Self::Prism => instances_dir_setting(data_dir, "prismlauncher.cfg"),
+            Self::MultiMc => instances_dir_setting(data_dir, "multimc.cfg"),
+            Self::Atlauncher | Self::Gdlauncher => data_dir.join("instances"),
+            // The official launcher has no instances, profiles point at a game directory.
+            Self::Vanilla => data_dir.join("instances"),
+            Self::Server => data_dir.to_path_buf(),
+        }
+    }
+
+    // The folder an instance called `name` lives in.
+    pub fn instance_dir(self, instances_dir: &Path, name: &str) -> PathBuf {
+        match self {
+            Self::Atlauncher => atlauncher::instance_dir(instances_dir, name),
+            _ => instances_dir.join(name),
+        }
+    }
+
+    // Launcher version, where the launcher writes it somewhere readable. Prism,
+    // MultiMC and ATLauncher only print it at the top of their logs.
+    fn version(self, data_dir: &Path) -> Option<String> {
+        let from_log = |logs: &[&str], key: &str| {
+            logs.iter()
+                .filter_map(|log| fs::read_to_string(data_dir.join(log)).ok())
+                .find_map(|contents| version_in_log(&contents, key))
+        };
+        match self {
+            Self::Vanilla => {
+                let profiles = fs::read_to_string(data_dir.join("launcher_profiles.json")).ok()?;
+                let profiles: serde_json::Value = serde_json::from_str(&profiles).ok()?;
+                profiles["launcherVersion"]["name"]
+                    .as_str()
+                    .map(str::to_string)
+            }
+            Self::Prism => from_log(
+                &["logs/PrismLauncher-0.log", "PrismLauncher-0.log"],
+                "Version",
+            ),
+            Self::MultiMc => from_log(&["MultiMC-0.log"], "Version"),
+            Self::Atlauncher => from_log(&["logs/atlauncher.log"], "ATLauncher Version"),
+            _ => None,
+        }
+    }
+
+    // Only for a launcher that is installed, anything else goes through `--target`.
+    pub fn profile_dir(self, env: &Environment) -> Result<PathBuf> {
+        let dirs = self.data_dirs(env);
+        let Some(data_dir) = dirs.iter().find(|d| d.is_dir()) else {
+            let looked: Vec<String> = dirs.iter().map(|d| d.display().to_string()).collect();
+            bail!(
+                "{} is not installed here (looked in {}). Pass --target <dir> to install into a directory of your choice",
+                self,
+                match looked.is_empty() {
+                    true => "no known places".to_string(),
+                    false => looked.join(", "),
+                }
+            );
+        };
+        Ok(self.instances_dir(data_dir))
+    }
+}
+
+pub struct Detected {
+    pub launcher: Launcher,
+    pub data_dir: PathBuf,
+    pub version: Option<String>,
+}
+
+// Launchers whose data directory exists on this machine.
+pub fn detect(env: &Environment) -> Vec<Detected> {
+    Launcher::ALL
+        .into_iter()
+        .filter_map(|launcher| {
+            let data_dir = launcher.data_dirs(env).into_iter().find(|d| d.is_dir())?;
+            Some(Detected {
+                launcher,
+                version: launcher.version(&data_dir),
+                data_dir,
+            })
+        })
+        .collect()
+}
+
+// The first `<key> : <version>` line near the top of a launcher log.
+fn version_in_log(contents: &str, key: &str) -> Option<String> {
+    contents.lines().take(100).find_map(|line| {
+        let (_, rest) = line.split_once(key)?;
+        let value = rest
+            .trim_start()
+            .strip_prefix(':')?
+            .split_whitespace()
+            .next()?;
+        Some(value.to_string())
+    })
+}
+
+// Prism and MultiMC let users move the instance folder, honour `InstanceDir`.
+fn instances_dir_setting(data_dir: &Path, cfg: &str) -> PathBuf {
+    let configured = fs::read_to_string(data_dir.join(cfg))
+        .ok()
+        .and_then(|cfg| {
+            cfg.lines()
+                .find_map(|l| l.trim().strip_prefix("InstanceDir=").map(str::to_string))
+        })
+        .filter(|dir| !dir.is_empty());
+    match configured {
+        Some(dir) => data_dir.join(dir),
+        None => data_dir.join("instances"),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Windows,
+    MacOs,
+    Linux,
+}
+
+// The bits of the process environment that launcher lookup depends on, kept
+// separate so the resolver can run against any platform and set of variables.
+pub struct Environment {
+    pub platform: Platform,
+    vars: HashMap<String, String>,
+}
+
+impl Environment {
+    pub fn current() -> Self {
+        let platform = if cfg!(windows) {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Linux
+        };
+        Self::new(platform, env::vars())
+    }
+
+    pub fn new<K, V>(platform: Platform, vars: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        Self {
+            platform,
+            vars: vars
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        }
+    }
+
+    fn path(&self, name: &str) -> Option<PathBuf> {
+        self.vars
+            .get(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    }
+
+    pub fn home(&self) -> Option<PathBuf> {
+        match self.platform {
+            Platform::Windows => self.path("USERPROFILE").or_else(|| {
+                let drive = self.vars.get("HOMEDRIVE")?;
+                let path = self.vars.get("HOMEPATH")?;
+                Some(PathBuf::from(format!("{drive}{path}")))
+            }),
+            _ => self.path("HOME"),
+        }
+    }
+
+    // `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+    pub fn config_home(&self) -> Option<PathBuf> {
+        self.path("XDG_CONFIG_HOME")
+            .filter(|p| p.is_absolute())
+            .or_else(|| self.home().map(|h| h.join(".config")))
+    }
+
+    // `$XDG_DATA_HOME`, falling back to `~/.local/share` as the spec says.
+    pub fn data_home(&self) -> Option<PathBuf> {
+        self.path("XDG_DATA_HOME")
+            .filter(|p| p.is_absolute())
+            .or_else(|| self.home().map(|h| h.join(".local/share")))
+    }
+}
+
+impl FromStr for Launcher {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "modrinth" => Ok(Self::Modrinth),
+            "curseforge" => Ok(Self::CurseForge),
+            "prism" => Ok(Self::Prism),
+            "multimc" => Ok(Self::MultiMc),
+            "atlauncher" => Ok(Self::Atlauncher),
+            "gdlauncher" => Ok(Self::Gdlauncher),
+            "vanilla" => Ok(Self::Vanilla),
+            "server" => Ok(Self::Server),
+            _ => bail!(
+                "Unknown launcher '{}', expected one of modrinth, curseforge, prism, multimc, atlauncher, gdlauncher, vanilla or server",
+                s
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Launcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Modrinth => "Modrinth",
+            Self::CurseForge => "CurseForge",
+            Self::Prism => "Prism",
+            Self::MultiMc => "MultiMC",
+            Self::Atlauncher => "ATLauncher",
+            Self::Gdlauncher => "GDLauncher",
+            Self::Vanilla => "Minecraft Launcher",
+            Self::Server => "Server",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(platform: Platform, vars: &[(&str, &str)]) -> Environment {
+        Environment::new(platform, vars.iter().copied())
+    }
+
+    #[test]
+    fn windows_uses_appdata_and_userprofile() {
+        let env = env(
+            Platform::Windows,
+            &[
+                ("APPDATA", r"C:\Users\steve\AppData\Roaming"),
+                ("USERPROFILE", r"C:\Users\steve"),
+            ],
+        );
+        let appdata = PathBuf::from(r"C:\Users\steve\AppData\Roaming");
+        assert_eq!(
+            Launcher::Modrinth.data_dirs(&env),
+            [
+                appdata.join("ModrinthApp"),
+                appdata.join("com.modrinth.theseus")
+            ]
+        );
+        assert_eq!(
+            Launcher::Prism.data_dirs(&env),
+            [appdata.join("PrismLauncher")]
+        );
+        assert_eq!(
+            Launcher::CurseForge.data_dirs(&env),
+            [PathBuf::from(r"C:\Users\steve")
+                .join("curseforge")
+                .join("minecraft")]
+        );
+    }
+
+    #[test]
+    fn windows_home_falls_back_to_homedrive() {
+        let env = env(
+            Platform::Windows,
+            &[("HOMEDRIVE", "D:"), ("HOMEPATH", r"\Users\alex")],
+        );
+        assert_eq!(env.home(), Some(PathBuf::from(r"D:\Users\alex")));
+        assert_eq!(
+            Launcher::MultiMc.data_dirs(&env),
+            [PathBuf::from(r"D:\Users\alex").join("MultiMC")]
+        );
+        // Without APPDATA the launchers that live there have no candidates.
+        assert!(Launcher::Vanilla.data_dirs(&env).is_empty());
+    }
+
+    #[test]
+    fn windows_ignores_empty_variables() {
+        let env = env(
+            Platform::Windows,
+            &[
+                ("USERPROFILE", ""),
+                ("HOMEDRIVE", "C:"),
+                ("HOMEPATH", r"\Users\steve"),
+            ],
+        );
+        assert_eq!(env.home(), Some(PathBuf::from(r"C:\Users\steve")));
+    }
+
+    #[test]
+    fn macos_uses_application_support() {
+        let env = env(Platform::MacOs, &[("HOME", "/Users/steve")]);
+        let support = PathBuf::from("/Users/steve/Library/Application Support");
+        assert_eq!(
+            Launcher::Prism.data_dirs(&env),
+            [support.join("PrismLauncher")]
+        );
+        assert_eq!(
+            Launcher::Vanilla.data_dirs(&env),
+            [support.join("minecraft")]
+        );
+        assert_eq!(
+            Launcher::CurseForge.data_dirs(&env),
+            [PathBuf::from("/Users/steve/Documents/curseforge/minecraft")]
+        );
+    }
+
+    #[test]
+    fn linux_uses_absolute_xdg_data_home() {
+        let env = env(
+            Platform::Linux,
+            &[("HOME", "/home/steve"), ("XDG_DATA_HOME", "/data")],
+        );
+        assert_eq!(env.data_home(), Some(PathBuf::from("/data")));
+        assert_eq!(
+            Launcher::Prism.data_dirs(&env)[0],
+            PathBuf::from("/data/PrismLauncher")
+        );
+    }
+
+    #[test]
+    fn linux_ignores_relative_xdg_dirs() {
+        let env = env(
+            Platform::Linux,
+            &[
+                ("HOME", "/home/steve"),
+                ("XDG_DATA_HOME", "relative/share"),
+                ("XDG_CONFIG_HOME", "relative/config"),
+            ],
+        );
+        assert_eq!(
+            env.data_home(),
+            Some(PathBuf::from("/home/steve/.local/share"))
+        );
+        assert_eq!(
+            Launcher::Gdlauncher.data_dirs(&env),
+            [PathBuf::from("/home/steve/.config/gdlauncher_next")]
+        );
+    }
+
+    #[test]
+    fn linux_includes_flatpak_dirs() {
+        let env = env(Platform::Linux, &[("HOME", "/home/steve")]);
+        assert_eq!(
+            Launcher::Prism.data_dirs(&env),
+            [
+                PathBuf::from("/home/steve/.local/share/PrismLauncher"),
+                PathBuf::from(
+                    "/home/steve/.var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher"
+                ),
+            ]
+        );
+        assert!(Launcher::Modrinth.data_dirs(&env).contains(&PathBuf::from(
+            "/home/steve/.var/app/com.modrinth.ModrinthApp/data/ModrinthApp"
+        )));
+        assert!(Launcher::Atlauncher
+            .data_dirs(&env)
+            .contains(&PathBuf::from(
+                "/home/steve/.var/app/com.atlauncher.ATLauncher/data/atlauncher"
+            )));
+        assert!(Launcher::Vanilla.data_dirs(&env).contains(&PathBuf::from(
+            "/home/steve/.var/app/com.mojang.Minecraft/.minecraft"
+        )));
+    }
+
+    #[test]
+    fn reads_versions_from_logs() {
+        let prism = "0.000 I | Prism Launcher, (c) 2022-2024 Prism Launcher Contributors
+0.000 I | Version                    : 8.4
+0.000 I | Platform                   : linux
+0.001 I | Java version               : 17.0.9";
+        assert_eq!(version_in_log(prism, "Version"), Some("8.4".to_string()));
+        let atlauncher = "[14:02:11] INFO (App): ATLauncher Version: 3.4.36.4
+[14:02:11] INFO (App): Java Version: 17.0.9";
+        assert_eq!(
+            version_in_log(atlauncher, "ATLauncher Version"),
+            Some("3.4.36.4".to_string())
+        );
+        assert_eq!(version_in_log("no version here", "Version"), None);
+    }
+
+    #[test]
+    fn no_home_means_no_candidates() {
+        let env = env(Platform::Linux, &[]);
+        for launcher in Launcher::ALL {
+            assert!(launcher.data_dirs(&env).is_empty(), "{launcher}");
+        }
+    }
+}
@@ -1,124 +1,568 @@
+mod atlauncher;
+mod channel;
+mod cli;
+mod config;
+mod curseforge;
+mod download;
+mod extract;
+mod gdlauncher;
+mod gitea;
+mod github;
+mod gitlab;
+mod install;
+mod launcher;
+mod manifest;
+mod metadata;
+mod modrinth;
+mod pack;
+mod preserve;
+mod prism;
+mod registry;
+mod repository;
+mod server;
+mod snapshot;
+mod source;
+mod static_index;
+mod uninstall;
+mod vanilla;
+mod verify;
+
 use anyhow::{Context, Result};
-use indicatif::{ProgressBar, ProgressStyle};
-use octocrab::Octocrab;
-use reqwest;
-use reqwest::Url;
-use std::env;
+use channel::Channel;
+use cli::{Args, Command};
+use config::Config;
+use launcher::{Detected, Environment, Launcher};
+use pack::PackInfo;
+use preserve::PreservePolicy;
+use registry::{InstanceRecord, Registry};
+use source::Source;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
-use std::str::FromStr;
-use tokio;
-use zip::ZipArchive;
-
-fn remove_dir_contents<P: AsRef<Path>>(path: P) -> Result<()> {
-    for entry in fs::read_dir(path)? {
-        let entry = entry?;
-        let path = entry.path();
-        if path.is_dir() {
-            fs::remove_dir_all(path)?;
-        } else {
-            fs::remove_file(path)?;
+
+fn prompt(message: &str) -> Result<String> {
+    print!("{}", message);
+    io::stdout().flush().context("Failed to flush stdout")?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read user input")?;
+    Ok(input.trim().to_string())
+}
+
+// Uses `--launcher` if given, otherwise offers the launchers installed here.
+fn choose_launcher(args: &Args, env: &Environment) -> Result<Launcher> {
+    if let Some(launcher) = args.launcher {
+        return Ok(launcher);
+    }
+
+    let detected = launcher::detect(env);
+    match detected.as_slice() {
+        [] => anyhow::bail!(
+            "No supported launcher was found on this machine. To install somewhere else, pass --target <dir> and --launcher <launcher>"
+        ),
+        [only] => {
+            println!(
+                "Found {} at {}, using it.",
+                describe(only),
+                only.data_dir.display()
+            );
+            return Ok(only.launcher);
         }
+        _ => {}
+    }
+
+    println!("Which launcher do you use?");
+    for (i, found) in detected.iter().enumerate() {
+        println!(
+            "{}. {} ({})",
+            i + 1,
+            describe(found),
+            found.data_dir.display()
+        );
+    }
+    let input = prompt(&format!("Enter your choice (1-{}): ", detected.len()))?;
+    let index: usize = input.parse().context("Invalid choice")?;
+    detected
+        .get(index.wrapping_sub(1))
+        .map(|d| d.launcher)
+        .context("Invalid choice")
+}
+
+fn describe(detected: &Detected) -> String {
+    match &detected.version {
+        Some(version) => format!("{} {}", detected.launcher, version),
+        None => detected.launcher.to_string(),
+    }
+}
+
+struct Instance {
+    name: String,
+    launcher: Launcher,
+    dir: PathBuf,
+    pinned: Option<String>,
+}
+
+// Instance names become directory names, both in the launcher and under `data_dir`.
+fn check_instance_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains(['/', '\\']) || name == "." || name == ".." {
+        anyhow::bail!("Invalid instance name '{}'", name);
     }
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let octocrab = Octocrab::builder()
-        .build()
-        .context("Failed to build Octocrab client")?;
-    let repo = octocrab.repos("thebearodactyl", "originalife-s4");
-    let latest_release = repo
-        .releases()
-        .get_latest()
-        .await
-        .context("Failed to fetch latest release")?;
+// Picks the instance from `--target`, `--instance` or the config, reusing the
+// launcher and directory the manager recorded for it last time.
+fn resolve_instance(args: &Args, config: &Config) -> Result<Instance> {
+    // A configured `target_dir` is only a default, `--instance` still wins over it.
+    let target = match &args.instance {
+        Some(_) => args.target.as_ref(),
+        None => args.target.as_ref().or(config.target_dir.as_ref()),
+    };
+    if let Some(dir) = target {
+        return explicit_instance(args, config, dir);
+    }
 
-    println!("Which launcher do you use?");
-    println!("1. Modrinth");
-    println!("2. CurseForge");
-    println!("3. Prism");
-    print!("Enter your choice (1-3): ");
-    io::stdout().flush().context("Failed to flush stdout")?;
+    let name = args
+        .instance
+        .clone()
+        .unwrap_or_else(|| config.instance_name.clone());
+    check_instance_name(&name)?;
 
-    let mut choice = String::new();
-    io::stdin()
-        .read_line(&mut choice)
-        .context("Failed to read user input")?;
-    let choice = choice.trim();
+    let registry = Registry::load(&config.data_dir)?;
+    let known = registry
+        .find(&name)
+        .into_iter()
+        .filter(|r| args.launcher.is_none_or(|l| l == r.launcher))
+        .collect::<Vec<_>>();
+    if let [record] = known.as_slice() {
+        println!(
+            "Using {} ({}) at {}",
+            record.name,
+            record.launcher,
+            record.path.display()
+        );
+        return Ok(Instance {
+            name,
+            launcher: record.launcher,
+            dir: record.path.clone(),
+            pinned: record.pinned.clone(),
+        });
+    }
 
-    let artifact_name = match choice {
-        "1" => "updated-pack-modrinth.zip",
-        "2" => "updated-pack-curseforge.zip",
-        _ => "updated-pack-prism.zip",
+    if args.launcher == Some(Launcher::Server) {
+        anyhow::bail!("A server has no launcher directory, pass --target <dir>");
+    }
+    let env = Environment::current();
+    let launcher = choose_launcher(args, &env)?;
+    Ok(Instance {
+        dir: launcher.instance_dir(&launcher.profile_dir(&env)?, &name),
+        name,
+        launcher,
+        pinned: None,
+    })
+}
+
+// An instance in a directory the user named, no launcher lookup involved. The
+// launcher only decides which artifact gets installed there.
+fn explicit_instance(args: &Args, config: &Config, dir: &Path) -> Result<Instance> {
+    let dir = std::path::absolute(dir).context("Failed to resolve target directory")?;
+    let name = match &args.instance {
+        Some(name) => name.clone(),
+        None => dir
+            .file_name()
+            .context("Target directory has no name")?
+            .to_string_lossy()
+            .into_owned(),
     };
+    check_instance_name(&name)?;
 
-    if let Some(asset) = latest_release
-        .assets
-        .iter()
-        .find(|a| a.name == artifact_name)
-    {
-        let client = reqwest::Client::new();
-        let url = Url::from_str(asset.browser_download_url.as_str()).expect("Invalid URL");
-        let total_size = asset.size;
-
-        let pb = ProgressBar::new(total_size as u64);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .expect("fuck.")
-            .progress_chars("#>-"));
-
-        let mut response = client
-            .get(url)
-            .send()
-            .await
-            .context("Failed to send request")?;
-        let mut content = Vec::with_capacity(total_size as usize);
-
-        while let Some(chunk) = response.chunk().await.context("Failed to read chunk")? {
-            content.extend_from_slice(&chunk);
-            pb.inc(chunk.len() as u64);
+    let registry = Registry::load(&config.data_dir)?;
+    let recorded = registry.instances.iter().find(|r| r.path == dir);
+    let launcher = match args.launcher.or(recorded.map(|r| r.launcher)) {
+        Some(launcher) => launcher,
+        None => choose_launcher(args, &Environment::current())?,
+    };
+
+    println!("Using {} ({}) at {}", name, launcher, dir.display());
+    Ok(Instance {
+        name,
+        launcher,
+        dir,
+        pinned: recorded.and_then(|r| r.pinned.clone()),
+    })
+}
+
+fn record_install(config: &Config, instance: &Instance, source: String) -> Result<()> {
+    let mut registry = Registry::load(&config.data_dir)?;
+    registry.upsert(InstanceRecord {
+        name: instance.name.clone(),
+        launcher: instance.launcher,
+        path: instance.dir.clone(),
+        source: Some(source),
+        pinned: instance.pinned.clone(),
+        updated_at: chrono::Local::now().to_rfc3339(),
+    });
+    registry.save(&config.data_dir)
+}
+
+fn list(config: &Config) -> Result<()> {
+    let registry = Registry::load(&config.data_dir)?;
+    if registry.instances.is_empty() {
+        println!("No instances installed yet.");
+        return Ok(());
+    }
+
+    for record in &registry.instances {
+        let pinned = match &record.pinned {
+            Some(tag) => format!(" (pinned to {})", tag),
+            None => String::new(),
+        };
+        println!(
+            "{} ({}) - {}{} - {} - {}",
+            record.name,
+            record.launcher,
+            record.source.as_deref().unwrap_or("unknown"),
+            pinned,
+            record.updated_at,
+            record.path.display()
+        );
+    }
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+async fn releases(source: &Source) -> Result<()> {
+    let releases = source
+        .releases(usize::MAX)
+        .await
+        .context("Failed to fetch releases")?;
+    if releases.is_empty() {
+        println!("No releases published yet.");
+        return Ok(());
+    }
+
+    for release in releases {
+        let date = release
+            .published_at
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "unpublished".to_string());
+        let kind = match release.prerelease {
+            true => " (prerelease)",
+            false => "",
+        };
+        println!("{} - {}{}", release.tag, date, kind);
+        for asset in release
+            .assets
+            .iter()
+            .filter(|a| a.name.starts_with("updated-pack-") && a.name.ends_with(".zip"))
+        {
+            let size = asset.size.map_or("size unknown".to_string(), format_size);
+            println!("  {}: {}", asset.name, size);
         }
+    }
+    println!("Install one with `update --tag <tag>`.");
+    Ok(())
+}
 
-        pb.finish_with_message("Download completed");
+// Snapshots the current instance, installs `archive` (from `source`) over it
+// and trims old snapshots.
+async fn replace_instance(
+    archive: &Path,
+    instance: &Instance,
+    config: &Config,
+    policy: &PreservePolicy,
+    source: &str,
+    incremental: bool,
+) -> Result<()> {
+    // Planning, the snapshot and the saved launcher files all have to see the
+    // instance as it was before an interrupted update, not half of it.
+    install::recover(&instance.dir)?;
 
-        let temp_file = PathBuf::from(artifact_name);
-        fs::write(&temp_file, content).context("Failed to write temporary file")?;
+    // A snapshot identical to the last one would only push an older, still
+    // useful one out of `snapshot_keep`.
+    let previous = Registry::load(&config.data_dir)?
+        .instances
+        .into_iter()
+        .find(|r| r.path == instance.dir)
+        .and_then(|r| r.source);
+    let changes = install::has_changes(archive, &instance.dir, policy, incremental)?;
+    let snapshot_dir = config.snapshot_dir(&instance.name, &instance.dir);
+    if changes || previous.as_deref() != Some(source) {
+        if let Some(snapshot) = snapshot::create(&instance.dir, &snapshot_dir, policy)
+            .context("Failed to snapshot the current instance")?
+        {
+            println!("Saved snapshot {} of the current instance", snapshot.id);
+        }
+    } else {
+        println!("No files change, skipping the snapshot");
+    }
 
-        let profile_dir = match choice {
-            "1" => env::var("APPDATA").context("Failed to get APPDATA")? + r"\ModrinthApp\profiles",
-            "2" => {
-                env::var("HOMEDRIVE").context("Failed to get HOMEDRIVE")?
-                    + &env::var("HOMEPATH").context("Failed to get HOMEPATH")?
-                    + r"\curseforge\minecraft\Instances"
-            }
-            "3" => {
-                env::var("APPDATA").context("Failed to get APPDATA")? + r"\PrismLauncher\instances"
+    let saved = metadata::capture(instance.launcher, &instance.dir)?;
+    install::install(archive, &instance.dir, policy, incremental)
+        .context("Failed to install update")?;
+    // The files are in place from here on, launcher trouble must not undo
+    // the bookkeeping below.
+    if let Err(error) = metadata::register(
+        instance.launcher,
+        &instance.dir,
+        &instance.name,
+        config,
+        saved,
+    ) {
+        println!(
+            "Warning: the update was installed but the launcher could not be updated: {:#}",
+            error
+        );
+    }
+    if instance.launcher == Launcher::Server {
+        if let Err(error) = install_server_launcher(instance, config).await {
+            println!(
+                "Warning: the update was installed but the server launcher could not be set up: {:#}",
+                error
+            );
+        }
+    }
+    snapshot::prune(&snapshot_dir, config.snapshot_keep).context("Failed to prune snapshots")
+}
+
+async fn install_server_launcher(instance: &Instance, config: &Config) -> Result<()> {
+    let info =
+        PackInfo::read(&instance.dir, &config.pack).context("Failed to read pack metadata")?;
+    server::install_launcher(&instance.dir, &info, &config.server).await
+}
+
+async fn restore(args: &Args, config: &Config, wanted: Option<String>) -> Result<()> {
+    let instance = resolve_instance(args, config)?;
+    let policy = &PreservePolicy::new(&config.preserve, instance.launcher);
+    let snapshots = snapshot::list(&config.snapshot_dir(&instance.name, &instance.dir))?;
+    if snapshots.is_empty() {
+        println!("No snapshots found for '{}'.", instance.name);
+        return Ok(());
+    }
+
+    let wanted = match wanted {
+        Some(id) => id,
+        None => {
+            println!("Available snapshots:");
+            for (i, snapshot) in snapshots.iter().enumerate() {
+                println!("{}. {}", i + 1, snapshot.id);
             }
-            _ => anyhow::bail!("Invalid choice"),
-        };
+            let input = prompt(&format!(
+                "Enter the snapshot to restore (1-{}): ",
+                snapshots.len()
+            ))?;
+            let index: usize = input.parse().context("Invalid choice")?;
+            snapshots
+                .get(index.wrapping_sub(1))
+                .context("Invalid choice")?
+                .id
+                .clone()
+        }
+    };
+    let chosen = snapshots
+        .iter()
+        .find(|s| s.id == wanted)
+        .with_context(|| format!("No snapshot named '{}'", wanted))?;
+
+    if args.dry_run {
+        let file = fs::File::open(&chosen.path).context("Failed to open snapshot")?;
+        return install::preview(file, &instance.dir, policy, false);
+    }
 
-        let target_dir = PathBuf::from(&profile_dir).join("Originalife Season 4");
-        if target_dir.exists() {
-            remove_dir_contents(&target_dir).context("Failed to clean target directory")?;
-        } else {
-            fs::create_dir_all(&target_dir).context("Failed to create target directory")?;
+    // Copy it out first, pruning after the restore may remove the original.
+    let temp_file = PathBuf::from(format!("snapshot-{}.zip", chosen.id));
+    fs::copy(&chosen.path, &temp_file).context("Failed to copy snapshot")?;
+    let source = format!("snapshot {}", chosen.id);
+    let result = replace_instance(&temp_file, &instance, config, policy, &source, false).await;
+    fs::remove_file(&temp_file).context("Failed to remove temporary file")?;
+    result?;
+    record_install(config, &instance, source)?;
+
+    println!("Restored snapshot {}.", chosen.id);
+    Ok(())
+}
+
+fn uninstall(args: &Args, config: &Config) -> Result<()> {
+    let instance = resolve_instance(args, config)?;
+    let target_dir = &instance.dir;
+    let snapshot_dir = config.snapshot_dir(&instance.name, &instance.dir);
+    let downloads = uninstall::cached_downloads()?;
+    let exportable = uninstall::exportable(target_dir);
+
+    println!("This will remove:");
+    if target_dir.exists() {
+        println!("  {}", target_dir.display());
+    }
+    if snapshot_dir.exists() {
+        println!("  {}", snapshot_dir.display());
+    }
+    for file in &downloads {
+        println!("  {}", file.display());
+    }
+    if args.dry_run {
+        for dir in &exportable {
+            println!("Would offer to export {}", dir.display());
         }
+        return Ok(());
+    }
 
-        let file = fs::File::open(&temp_file).context("Failed to open temporary file")?;
-        let mut archive = ZipArchive::new(file).context("Failed to create ZIP archive")?;
-        archive
-            .extract(&target_dir)
-            .context("Failed to extract ZIP archive")?;
+    if !args.yes && !prompt("Continue? [y/N]: ")?.eq_ignore_ascii_case("y") {
+        println!("Uninstall cancelled.");
+        return Ok(());
+    }
 
-        fs::remove_file(temp_file).context("Failed to remove temporary file")?;
+    if !exportable.is_empty() {
+        let export = args.yes
+            || !prompt("Keep worlds and screenshots in an export folder? [Y/n]: ")?
+                .eq_ignore_ascii_case("n");
+        if export {
+            let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+            let export_dir = config
+                .data_dir
+                .join("exports")
+                .join(format!("{}-{}", instance.name, stamp));
+            uninstall::export(target_dir, &export_dir)?;
+        }
+    }
 
-        println!("Update completed successfully!");
-    } else {
+    metadata::unregister(instance.launcher, target_dir, &instance.name)?;
+    uninstall::remove_instance(target_dir)?;
+    if snapshot_dir.exists() {
+        fs::remove_dir_all(&snapshot_dir).context("Failed to remove snapshots")?;
+    }
+    uninstall::remove_files(&downloads)?;
+
+    let mut registry = Registry::load(&config.data_dir)?;
+    registry.remove(target_dir);
+    registry.save(&config.data_dir)?;
+
+    println!("Uninstalled {}.", instance.name);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse()?;
+    let config = Config::load()?;
+    let repository = repository::repository(args.repo.as_deref(), config.repository.as_deref())?;
+    let source = Source::new(&repository, &config)?;
+
+    match &args.command {
+        Command::Update => update(&args, &config, &source).await,
+        Command::Restore { snapshot } => restore(&args, &config, snapshot.clone()).await,
+        Command::Uninstall => uninstall(&args, &config),
+        Command::List => list(&config),
+        Command::Releases => releases(&source).await,
+    }
+}
+
+async fn update(args: &Args, config: &Config, source: &Source) -> Result<()> {
+    let mut instance = resolve_instance(args, config)?;
+    match args.tag.as_deref() {
+        Some("latest") => instance.pinned = None,
+        Some(tag) => instance.pinned = Some(tag.to_string()),
+        None => {}
+    }
+    let policy = &PreservePolicy::new(&config.preserve, instance.launcher);
+    let artifact_name = instance.launcher.artifact_name();
+    let channel = args.channel.unwrap_or(config.channel);
+
+    let download = match (&instance.pinned, channel) {
+        (Some(tag), _) => {
+            println!("Installing pinned release {}", tag);
+            let release = source
+                .release(tag)
+                .await
+                .with_context(|| format!("Failed to fetch release '{}'", tag))?;
+            download::release(&release, artifact_name, config).await?
+        }
+        (None, Channel::Stable) => {
+            let release = source
+                .latest_release()
+                .await
+                .context("Failed to fetch latest release")?;
+            download::release(&release, artifact_name, config).await?
+        }
+        (None, Channel::Beta) => {
+            let releases = source
+                .releases(1)
+                .await
+                .context("Failed to fetch releases")?;
+            match releases.iter().find(|r| !r.draft) {
+                Some(release) => download::release(release, artifact_name, config).await?,
+                None => None,
+            }
+        }
+        (None, Channel::Nightly) => channel::nightly(source, artifact_name)
+            .await?
+            .map(|nightly| download::nightly(nightly, artifact_name))
+            .transpose()?
+            .flatten(),
+    };
+    let Some(mut download) = download else {
         println!("No new release found or '{}' not available.", artifact_name);
+        return Ok(());
+    };
+
+    if download.checksum.is_none() {
+        if config.require_checksum {
+            anyhow::bail!("The release publishes no checksum for '{}'", artifact_name);
+        }
+        println!(
+            "Warning: the release publishes no checksum for '{}', skipping verification",
+            artifact_name
+        );
+    }
+
+    if let Some(key) = verify::public_key(config.public_key.as_deref())? {
+        let signature = download
+            .signature
+            .as_deref()
+            .with_context(|| format!("The release has no signature for '{}'", artifact_name))?;
+        let comment = verify::verify_signature(&download.content, signature, &key)?;
+        println!("Verified signature of {} ({})", artifact_name, comment);
+    } else {
+        println!("No public key is configured, release signatures are not checked");
     }
 
+    if instance.launcher == Launcher::Server {
+        let mut client_only = config.server.client_only.clone();
+        if let Some(contents) = &download.client_only {
+            client_only.extend(server::parse_client_only(contents));
+        }
+        download.content = server::server_pack(&download.content, &client_only)?;
+    }
+
+    if args.dry_run {
+        return install::preview(
+            io::Cursor::new(download.content),
+            &instance.dir,
+            policy,
+            true,
+        );
+    }
+
+    let temp_file = PathBuf::from(artifact_name);
+    fs::write(&temp_file, download.content).context("Failed to write temporary file")?;
+
+    let result = replace_instance(
+        &temp_file,
+        &instance,
+        config,
+        policy,
+        &download.source,
+        true,
+    )
+    .await;
+    fs::remove_file(&temp_file).context("Failed to remove temporary file")?;
+    result?;
+    record_install(config, &instance, download.source)?;
+
+    println!("Update completed successfully!");
     Ok(())
 }
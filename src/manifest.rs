@@ -0,0 +1,87 @@
+use crate::extract::Entry;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+// Kept at the instance root, records what the last install put there.
+pub const MANIFEST_FILE: &str = ".originalife-manifest.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    // Slash-separated path relative to the instance -> hex SHA-256.
+    pub files: BTreeMap<String, String>,
+}
+
+impl Manifest {
+    pub fn load(target: &Path) -> Result<Option<Self>> {
+        let path = target.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(path).context("Failed to read pack manifest")?;
+        serde_json::from_str(&contents)
+            .map(Some)
+            .context("Failed to parse pack manifest")
+    }
+
+    pub fn from_entries<R: io::Read + io::Seek>(
+        archive: &mut ZipArchive<R>,
+        entries: &[Entry],
+    ) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for entry in entries {
+            let name = entry_name(&entry.path);
+            if name == MANIFEST_FILE {
+                continue;
+            }
+            let mut file = archive.by_index(entry.index)?;
+            files.insert(name, hash_reader(&mut file)?);
+        }
+        Ok(Self { files })
+    }
+
+    // The manifest an archive carries itself, which snapshots do so a restore
+    // brings back what the install recorded rather than everything restored.
+    pub fn embedded<R: io::Read + io::Seek>(
+        archive: &mut ZipArchive<R>,
+        entries: &[Entry],
+    ) -> Result<Option<Self>> {
+        let Some(entry) = entries
+            .iter()
+            .find(|e| entry_name(&e.path) == MANIFEST_FILE)
+        else {
+            return Ok(None);
+        };
+        let file = archive.by_index(entry.index)?;
+        serde_json::from_reader(file)
+            .map(Some)
+            .context("Failed to parse the archive's pack manifest")
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files.keys().map(PathBuf::from)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&String> {
+        self.files.get(&entry_name(path))
+    }
+}
+
+pub fn entry_name(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn hash_reader<R: Read>(reader: &mut R) -> Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher).context("Failed to hash file")?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,80 @@
+use crate::atlauncher;
+use crate::config::Config;
+use crate::curseforge;
+use crate::gdlauncher;
+use crate::launcher::Launcher;
+use crate::modrinth;
+use crate::pack::PackInfo;
+use crate::prism;
+use crate::vanilla;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+// Launcher metadata the user may have customised, read before an update
+// replaces the instance so it can be merged back afterwards.
+pub enum Saved {
+    Prism(prism::Saved),
+    CurseForge(curseforge::Saved),
+    Atlauncher(atlauncher::Saved),
+    Gdlauncher(gdlauncher::Saved),
+    None,
+}
+
+pub fn capture(launcher: Launcher, dir: &Path) -> Result<Saved> {
+    match launcher {
+        Launcher::Prism | Launcher::MultiMc => prism::capture(dir).map(Saved::Prism),
+        Launcher::CurseForge => curseforge::capture(dir).map(Saved::CurseForge),
+        Launcher::Atlauncher => atlauncher::capture(dir).map(Saved::Atlauncher),
+        Launcher::Gdlauncher => gdlauncher::capture(dir).map(Saved::Gdlauncher),
+        _ => Ok(Saved::None),
+    }
+}
+
+// Writes or merges the launcher's own records for a freshly installed instance.
+pub fn register(
+    launcher: Launcher,
+    dir: &Path,
+    name: &str,
+    config: &Config,
+    saved: Saved,
+) -> Result<()> {
+    let info = PackInfo::read(dir, &config.pack).context("Failed to read pack metadata")?;
+    match (launcher, saved) {
+        (Launcher::Prism, Saved::Prism(saved)) => {
+            prism::register(dir, name, &info, &config.prism, saved)
+                .context("Failed to write Prism instance metadata")
+        }
+        (Launcher::MultiMc, Saved::Prism(saved)) => {
+            prism::register(dir, name, &info, &config.prism, saved)
+                .context("Failed to write MultiMC instance metadata")
+        }
+        (Launcher::CurseForge, Saved::CurseForge(saved)) => {
+            curseforge::register(dir, name, &info, saved)
+                .context("Failed to write CurseForge instance metadata")
+        }
+        (Launcher::Modrinth, _) => modrinth::register(dir, name, &info, &config.modrinth)
+            .context("Failed to write Modrinth App profile metadata"),
+        (Launcher::Atlauncher, Saved::Atlauncher(saved)) => {
+            atlauncher::register(dir, name, &info, saved)
+                .context("Failed to write ATLauncher instance metadata")
+        }
+        (Launcher::Gdlauncher, Saved::Gdlauncher(saved)) => gdlauncher::register(dir, &info, saved)
+            .context("Failed to write GDLauncher instance metadata"),
+        (Launcher::Vanilla, _) => vanilla::register(dir, name, &info)
+            .context("Failed to add the Minecraft Launcher profile"),
+        _ => Ok(()),
+    }
+}
+
+// Removes launcher records kept outside the instance directory.
+pub fn unregister(launcher: Launcher, dir: &Path, name: &str) -> Result<()> {
+    match launcher {
+        Launcher::Prism | Launcher::MultiMc => {
+            prism::unregister(dir, name).context("Failed to remove instance group and icon")
+        }
+        Launcher::Modrinth => modrinth::unregister(dir),
+        Launcher::Vanilla => vanilla::unregister(dir, name)
+            .context("Failed to remove the Minecraft Launcher profile"),
+        _ => Ok(()),
+    }
+}
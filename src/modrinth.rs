@@ -0,0 +1,154 @@
+use crate::pack::PackInfo;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// The Modrinth App keeps its profile list in SQLite next to `profiles/`.
+const DATABASE: &str = "app.db";
+// Older app versions used a JSON file per profile instead.
+const LEGACY_PROFILE: &str = "profile.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ModrinthSettings {
+    // Path to an image to use as the profile icon.
+    pub icon: Option<PathBuf>,
+    // Group the profile is created in.
+    pub group: Option<String>,
+}
+
+pub fn register(
+    dir: &Path,
+    name: &str,
+    info: &PackInfo,
+    settings: &ModrinthSettings,
+) -> Result<()> {
+    let icon = install_icon(dir, settings)?;
+    let loader = info
+        .loader
+        .as_ref()
+        .map_or("vanilla".to_string(), |(loader, _)| loader.to_string());
+    let loader_version = info.loader.as_ref().map(|(_, version)| version.as_str());
+    let game_version = info.require_minecraft()?;
+
+    let legacy = dir.join(LEGACY_PROFILE);
+    if legacy.is_file() {
+        update_legacy_profile(
+            &legacy,
+            name,
+            game_version,
+            &loader,
+            loader_version,
+            icon.as_deref(),
+        )?;
+    }
+
+    let Some(database) = database(dir) else {
+        println!("Modrinth App database not found, the profile will appear once the app has been started and the instance imported.");
+        return Ok(());
+    };
+    let path = profile_path(dir)?;
+    let now = chrono::Utc::now().timestamp();
+    let groups = json!(settings.group.iter().collect::<Vec<_>>()).to_string();
+    let icon = icon.as_deref().map(|p| p.to_string_lossy().into_owned());
+    open(&database)?
+        .execute(
+            "INSERT INTO profiles (path, install_stage, name, icon_path, game_version, mod_loader, \
+             mod_loader_version, groups, created, modified, submitted_time_played, recent_time_played, \
+             override_extra_launch_args, override_custom_env_vars) \
+             VALUES (?1, 'installed', ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, 0, 0, '[]', '[]') \
+             ON CONFLICT (path) DO UPDATE SET install_stage = excluded.install_stage, \
+             name = excluded.name, icon_path = COALESCE(excluded.icon_path, profiles.icon_path), \
+             game_version = excluded.game_version, mod_loader = excluded.mod_loader, \
+             mod_loader_version = excluded.mod_loader_version, modified = excluded.modified",
+            params![path, name, icon, game_version, loader, loader_version, groups, now],
+        )
+        .context("Failed to register the profile with the Modrinth App (is it still running?)")?;
+    Ok(())
+}
+
+pub fn unregister(dir: &Path) -> Result<()> {
+    let Some(database) = database(dir) else {
+        return Ok(());
+    };
+    open(&database)?
+        .execute(
+            "DELETE FROM profiles WHERE path = ?1",
+            params![profile_path(dir)?],
+        )
+        .context("Failed to remove the profile from the Modrinth App")?;
+    Ok(())
+}
+
+// `<data dir>/profiles/<profile>` -> `<data dir>/app.db`
+fn database(dir: &Path) -> Option<PathBuf> {
+    let data_dir = dir.parent()?.parent()?;
+    Some(data_dir.join(DATABASE)).filter(|db| db.is_file())
+}
+
+fn open(database: &Path) -> Result<Connection> {
+    let connection =
+        Connection::open(database).context("Failed to open the Modrinth App database")?;
+    // The app may hold the database briefly while it's running.
+    connection
+        .busy_timeout(Duration::from_secs(5))
+        .context("Failed to configure the Modrinth App database")?;
+    Ok(connection)
+}
+
+// Profiles are keyed by their folder name inside `profiles/`.
+fn profile_path(dir: &Path) -> Result<String> {
+    Ok(dir
+        .file_name()
+        .context("Profile directory has no name")?
+        .to_string_lossy()
+        .into_owned())
+}
+
+fn update_legacy_profile(
+    path: &Path,
+    name: &str,
+    game_version: &str,
+    loader: &str,
+    loader_version: Option<&str>,
+    icon: Option<&Path>,
+) -> Result<()> {
+    let contents = fs::read_to_string(path).context("Failed to read profile.json")?;
+    let mut profile: Value =
+        serde_json::from_str(&contents).context("Failed to parse profile.json")?;
+    if !profile["metadata"].is_object() {
+        profile["metadata"] = json!({});
+    }
+
+    let metadata = &mut profile["metadata"];
+    metadata["name"] = json!(name);
+    metadata["game_version"] = json!(game_version);
+    metadata["loader"] = json!(loader);
+    metadata["loader_version"] = match loader_version {
+        Some(id) => json!({ "id": id, "url": "", "stable": true }),
+        None => Value::Null,
+    };
+    if let Some(icon) = icon {
+        metadata["icon"] = json!(icon);
+    }
+    metadata["date_modified"] = json!(chrono::Utc::now().to_rfc3339());
+    profile["install_stage"] = json!("installed");
+
+    let contents =
+        serde_json::to_string_pretty(&profile).context("Failed to serialize profile.json")?;
+    fs::write(path, contents).context("Failed to write profile.json")
+}
+
+fn install_icon(dir: &Path, settings: &ModrinthSettings) -> Result<Option<PathBuf>> {
+    let Some(icon) = &settings.icon else {
+        return Ok(None);
+    };
+    let extension = icon.extension().unwrap_or_default().to_string_lossy();
+    let destination = dir.join(format!("icon.{}", extension));
+    fs::copy(icon, &destination).context("Failed to copy profile icon")?;
+    Ok(Some(std::path::absolute(destination)?))
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Loader {
+    Forge,
+    NeoForge,
+    Fabric,
+    Quilt,
+}
+
+impl Loader {
+    // Component uid in Prism/MultiMC `mmc-pack.json`.
+    pub fn component_uid(self) -> &'static str {
+        match self {
+            Self::Forge => "net.minecraftforge",
+            Self::NeoForge => "net.neoforged",
+            Self::Fabric => "net.fabricmc.fabric-loader",
+            Self::Quilt => "org.quiltmc.quilt-loader",
+        }
+    }
+
+    fn from_component_uid(uid: &str) -> Option<Self> {
+        [Self::Forge, Self::NeoForge, Self::Fabric, Self::Quilt]
+            .into_iter()
+            .find(|l| l.component_uid() == uid)
+    }
+
+    // Key in `modrinth.index.json` dependencies.
+    fn from_modrinth_dependency(key: &str) -> Option<Self> {
+        match key {
+            "forge" => Some(Self::Forge),
+            "neoforge" => Some(Self::NeoForge),
+            "fabric-loader" => Some(Self::Fabric),
+            "quilt-loader" => Some(Self::Quilt),
+            _ => None,
+        }
+    }
+
+    // CurseForge writes loaders as `<name>-<version>`, e.g. `forge-47.2.0`.
+    fn from_curseforge_id(id: &str) -> Option<(Self, String)> {
+        let (name, version) = id.split_once('-')?;
+        let loader = match name {
+            "forge" => Self::Forge,
+            "neoforge" => Self::NeoForge,
+            "fabric" => Self::Fabric,
+            "quilt" => Self::Quilt,
+            _ => return None,
+        };
+        Some((loader, version.to_string()))
+    }
+}
+
+impl fmt::Display for Loader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Forge => "forge",
+            Self::NeoForge => "neoforge",
+            Self::Fabric => "fabric",
+            Self::Quilt => "quilt",
+        };
+        f.write_str(name)
+    }
+}
+
+// Values from the config that win over whatever the pack declares.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PackOverrides {
+    pub minecraft_version: Option<String>,
+    pub loader: Option<Loader>,
+    pub loader_version: Option<String>,
+}
+
+// Name the manager files its own launcher entries (icons, profiles) under.
+pub fn managed_key(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '-',
+        })
+        .collect();
+    format!("originalife-{slug}")
+}
+
+type MetadataParser = fn(&Value) -> PackInfo;
+
+// Game and mod loader versions the pack was built for.
+#[derive(Debug, Default, Clone)]
+pub struct PackInfo {
+    pub minecraft: Option<String>,
+    pub loader: Option<(Loader, String)>,
+}
+
+impl PackInfo {
+    // Reads whichever launcher metadata the release shipped in the instance.
+    pub fn read(dir: &Path, overrides: &PackOverrides) -> Result<Self> {
+        let sources: [(&str, MetadataParser); 4] = [
+            ("mmc-pack.json", Self::from_mmc_pack),
+            ("modrinth.index.json", Self::from_modrinth_index),
+            ("manifest.json", Self::from_curseforge_manifest),
+            ("minecraftinstance.json", Self::from_curseforge_instance),
+        ];
+
+        let mut info = Self::default();
+        for (file, parse) in sources {
+            let path = dir.join(file);
+            if !path.is_file() {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let Ok(json) = serde_json::from_str::<Value>(&contents) else {
+                continue;
+            };
+            let found = parse(&json);
+            info.minecraft = info.minecraft.or(found.minecraft);
+            info.loader = info.loader.or(found.loader);
+        }
+
+        if let Some(version) = &overrides.minecraft_version {
+            info.minecraft = Some(version.clone());
+        }
+        match (overrides.loader, &overrides.loader_version, &info.loader) {
+            (Some(loader), Some(version), _) => info.loader = Some((loader, version.clone())),
+            (Some(loader), None, Some((_, version))) => {
+                info.loader = Some((loader, version.clone()))
+            }
+            (None, Some(version), Some((loader, _))) => {
+                info.loader = Some((*loader, version.clone()))
+            }
+            _ => {}
+        }
+        Ok(info)
+    }
+
+    // Every launcher needs the game version, so a pack without one can't be set up.
+    pub fn require_minecraft(&self) -> Result<&str> {
+        self.minecraft.as_deref().context(
+            "The pack does not say which Minecraft version it needs, set `pack.minecraft_version`",
+        )
+    }
+
+    fn from_mmc_pack(json: &Value) -> Self {
+        let mut info = Self::default();
+        for component in json["components"].as_array().into_iter().flatten() {
+            let (Some(uid), Some(version)) =
+                (component["uid"].as_str(), component["version"].as_str())
+            else {
+                continue;
+            };
+            if uid == "net.minecraft" {
+                info.minecraft = Some(version.to_string());
+            } else if let Some(loader) = Loader::from_component_uid(uid) {
+                info.loader = Some((loader, version.to_string()));
+            }
+        }
+        info
+    }
+
+    fn from_modrinth_index(json: &Value) -> Self {
+        let mut info = Self::default();
+        for (key, version) in json["dependencies"].as_object().into_iter().flatten() {
+            let Some(version) = version.as_str() else {
+                continue;
+            };
+            if key == "minecraft" {
+                info.minecraft = Some(version.to_string());
+            } else if let Some(loader) = Loader::from_modrinth_dependency(key) {
+                info.loader = Some((loader, version.to_string()));
+            }
+        }
+        info
+    }
+
+    fn from_curseforge_manifest(json: &Value) -> Self {
+        let loaders = json["minecraft"]["modLoaders"].as_array();
+        let primary = loaders
+            .into_iter()
+            .flatten()
+            .find(|l| l["primary"].as_bool().unwrap_or(true));
+        Self {
+            minecraft: json["minecraft"]["version"].as_str().map(str::to_string),
+            loader: primary
+                .and_then(|l| l["id"].as_str())
+                .and_then(Loader::from_curseforge_id),
+        }
+    }
+
+    fn from_curseforge_instance(json: &Value) -> Self {
+        Self {
+            minecraft: json["gameVersion"].as_str().map(str::to_string),
+            loader: json["baseModLoader"]["name"]
+                .as_str()
+                .and_then(Loader::from_curseforge_id),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use crate::launcher::Launcher;
+use std::path::{Component, Path};
+
+// Player data that an update must never delete or overwrite.
+const DEFAULT_PRESERVE: &[&str] = &[
+    "saves/**",
+    "screenshots/**",
+    "schematics/**",
+    "options.txt",
+    "optionsof.txt",
+    "optionsshaders.txt",
+    "servers.dat",
+    "servers.dat_old",
+    "usercache.json",
+    "logs/**",
+    "crash-reports/**",
+];
+
+// Folders both the pack and the player put files in. Whatever the pack ships
+// there is updated with it, everything else is the player's.
+const SHARED: &[&str] = &["resourcepacks/**", "shaderpacks/**"];
+
+// Server state and the loader's server launcher, which the pack doesn't ship.
+const SERVER_PRESERVE: &[&str] = &[
+    "world/**",
+    "server.properties",
+    "whitelist.json",
+    "ops.json",
+    "banned-players.json",
+    "banned-ips.json",
+    "eula.txt",
+    "libraries/**",
+    "versions/**",
+    ".fabric/**",
+    ".quilt/**",
+    "server.jar",
+    "fabric-server-launch.jar",
+    "quilt-server-launch.jar",
+    "run.sh",
+    "run.bat",
+    "user_jvm_args.txt",
+    ".originalife-server.json",
+];
+
+pub struct PreservePolicy {
+    patterns: Vec<String>,
+}
+
+impl PreservePolicy {
+    pub fn new(extra: &[String], launcher: Launcher) -> Self {
+        let server: &[&str] = match launcher {
+            Launcher::Server => SERVER_PRESERVE,
+            _ => &[],
+        };
+        let patterns = DEFAULT_PRESERVE
+            .iter()
+            .chain(server)
+            .map(|p| p.to_string())
+            .chain(extra.iter().flat_map(|p| user_pattern(p)))
+            .collect();
+        Self { patterns }
+    }
+
+    // `in_pack` tells whether the file is part of the pack, which decides
+    // who owns it inside the shared folders.
+    pub fn is_preserved(&self, relative: &Path, in_pack: bool) -> bool {
+        let path = game_relative(relative);
+        self.patterns.iter().any(|p| glob_match(p, &path))
+            || (!in_pack && SHARED.iter().any(|p| glob_match(p, &path)))
+    }
+}
+
+// Only files are matched, so a pattern naming a directory (`journeymap/` or
+// just `journeymap`) has to cover everything below it as well.
+fn user_pattern(pattern: &str) -> Vec<String> {
+    let pattern = pattern.replace('\\', "/");
+    let pattern = pattern.trim_start_matches('/');
+    match pattern.strip_suffix('/') {
+        Some(dir) => vec![format!("{}/**", dir.trim_end_matches('/'))],
+        None if pattern.ends_with("**") => vec![pattern.to_string()],
+        None => vec![pattern.to_string(), format!("{pattern}/**")],
+    }
+}
+
+// Prism and MultiMC keep the game directory in `.minecraft/` (or `minecraft/`)
+// inside the instance, the other launchers use the instance directory itself.
+pub fn game_relative(relative: &Path) -> String {
+    let parts: Vec<String> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    let skip = match parts.first().map(String::as_str) {
+        Some(".minecraft") | Some("minecraft") if parts.len() > 1 => 1,
+        _ => 0,
+    };
+    parts[skip..].join("/")
+}
+
+// Supports `*` and `?` within a path segment and `**` across segments.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => {
+            name.first().is_some_and(|n| n.eq_ignore_ascii_case(c))
+                && match_segment(rest, &name[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(extra: &[&str]) -> PreservePolicy {
+        let extra: Vec<String> = extra.iter().map(|p| p.to_string()).collect();
+        PreservePolicy::new(&extra, Launcher::Prism)
+    }
+
+    #[test]
+    fn double_star_at_the_end() {
+        assert!(glob_match("saves/**", "saves/world/level.dat"));
+        assert!(glob_match("saves/**", "saves/level.dat"));
+        assert!(!glob_match("saves/**", "savesx/level.dat"));
+        assert!(!glob_match("saves/**", "config/saves/level.dat"));
+    }
+
+    #[test]
+    fn double_star_in_the_middle() {
+        assert!(glob_match("config/**/*.toml", "config/a.toml"));
+        assert!(glob_match("config/**/*.toml", "config/mod/sub/a.toml"));
+        assert!(!glob_match("config/**/*.toml", "config/mod/a.json"));
+        assert!(glob_match("**/options.txt", "options.txt"));
+    }
+
+    #[test]
+    fn single_segment_wildcards() {
+        assert!(glob_match("mods/*.jar", "mods/a.jar"));
+        assert!(!glob_match("mods/*.jar", "mods/sub/a.jar"));
+        assert!(glob_match("servers.dat?old", "servers.dat_old"));
+        assert!(!glob_match("servers.dat?old", "servers.datold"));
+        assert!(glob_match("Options.TXT", "options.txt"));
+    }
+
+    #[test]
+    fn strips_the_game_directory() {
+        assert_eq!(game_relative(Path::new(".minecraft/saves/a")), "saves/a");
+        assert_eq!(
+            game_relative(Path::new("minecraft/options.txt")),
+            "options.txt"
+        );
+        assert_eq!(game_relative(Path::new("saves/a")), "saves/a");
+        // A file called `minecraft` on its own is not a game directory.
+        assert_eq!(game_relative(Path::new("minecraft")), "minecraft");
+
+        let policy = policy(&[]);
+        assert!(policy.is_preserved(Path::new(".minecraft/saves/world/level.dat"), false));
+        assert!(policy.is_preserved(Path::new("minecraft/options.txt"), true));
+        assert!(!policy.is_preserved(Path::new(".minecraft/mods/a.jar"), false));
+    }
+
+    #[test]
+    fn shared_folders_belong_to_whoever_put_the_file_there() {
+        let policy = policy(&[]);
+        let pack = Path::new(".minecraft/resourcepacks/pack.zip");
+        assert!(!policy.is_preserved(pack, true));
+        assert!(policy.is_preserved(pack, false));
+        assert!(policy.is_preserved(Path::new("shaderpacks/mine.zip"), false));
+        // Player data outside the shared folders is kept even if the pack ships it.
+        assert!(policy.is_preserved(Path::new("options.txt"), true));
+    }
+
+    #[test]
+    fn user_patterns_cover_directories() {
+        let policy = policy(&["journeymap/", "/xaero", r"config\mine.toml"]);
+        assert!(policy.is_preserved(Path::new("journeymap/data/map.dat"), true));
+        assert!(policy.is_preserved(Path::new(".minecraft/xaero/world/a"), true));
+        assert!(policy.is_preserved(Path::new("xaero"), true));
+        assert!(policy.is_preserved(Path::new("config/mine.toml"), true));
+        assert!(!policy.is_preserved(Path::new("journeymapx/a"), true));
+    }
+}
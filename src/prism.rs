@@ -0,0 +1,273 @@
+use crate::pack::{self, PackInfo};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INSTANCE_CFG: &str = "instance.cfg";
+const MMC_PACK: &str = "mmc-pack.json";
+const GROUPS_FILE: &str = "instgroups.json";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PrismSettings {
+    // Group the instance is listed under in Prism.
+    pub group: Option<String>,
+    // Icon key, or a path to an image that gets copied into Prism's icons.
+    pub icon: Option<String>,
+    // JVM heap bounds in MiB.
+    pub min_memory: Option<u32>,
+    pub max_memory: Option<u32>,
+}
+
+// The user's `instance.cfg` from before the update, so their settings survive it.
+pub struct Saved {
+    cfg: Option<String>,
+}
+
+pub fn capture(dir: &Path) -> Result<Saved> {
+    let path = dir.join(INSTANCE_CFG);
+    let cfg = match path.is_file() {
+        true => Some(fs::read_to_string(&path).context("Failed to read instance.cfg")?),
+        false => None,
+    };
+    Ok(Saved { cfg })
+}
+
+pub fn register(
+    dir: &Path,
+    name: &str,
+    info: &PackInfo,
+    settings: &PrismSettings,
+    saved: Saved,
+) -> Result<()> {
+    let icon_key = install_icon(dir, name, settings)?;
+    write_instance_cfg(dir, name, icon_key.as_deref(), settings, saved)?;
+    write_mmc_pack(dir, info)?;
+    if let Some(group) = &settings.group {
+        update_groups(dir, Some(group))?;
+    }
+    Ok(())
+}
+
+pub fn unregister(dir: &Path, name: &str) -> Result<()> {
+    update_groups(dir, None)?;
+    if let Some(icons) = icons_dir(dir) {
+        let icon = icons.join(format!("{}.png", pack::managed_key(name)));
+        if icon.exists() {
+            fs::remove_file(&icon).context("Failed to remove instance icon")?;
+        }
+    }
+    Ok(())
+}
+
+// Prism's own `instance.cfg` is a flat Qt settings file under `[General]`.
+struct InstanceCfg {
+    entries: Vec<(String, String)>,
+}
+
+impl InstanceCfg {
+    fn parse(text: &str) -> Self {
+        let entries = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.starts_with('['))
+            .filter_map(|l| l.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Self { entries }
+    }
+
+    fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+        match self.entries.iter_mut().find(|(k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key.to_string(), value)),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::from("[General]\n");
+        for (key, value) in &self.entries {
+            out.push_str(&format!("{key}={value}\n"));
+        }
+        out
+    }
+}
+
+fn write_instance_cfg(
+    dir: &Path,
+    name: &str,
+    icon_key: Option<&str>,
+    settings: &PrismSettings,
+    saved: Saved,
+) -> Result<()> {
+    let path = dir.join(INSTANCE_CFG);
+    let shipped = match path.is_file() {
+        true => fs::read_to_string(&path).context("Failed to read instance.cfg")?,
+        false => String::new(),
+    };
+    let mut cfg = InstanceCfg::parse(&shipped);
+
+    let mut managed = vec![
+        ("InstanceType", "OneSix".to_string()),
+        ("name", name.to_string()),
+    ];
+    if let Some(icon_key) = icon_key {
+        managed.push(("iconKey", icon_key.to_string()));
+    }
+    if settings.min_memory.is_some() || settings.max_memory.is_some() {
+        managed.push(("OverrideMemory", "true".to_string()));
+    }
+    if let Some(min) = settings.min_memory {
+        managed.push(("MinMemAlloc", min.to_string()));
+    }
+    if let Some(max) = settings.max_memory {
+        managed.push(("MaxMemAlloc", max.to_string()));
+    }
+
+    // Anything the player set (Java path, window size, play time...) wins over the pack.
+    if let Some(previous) = saved.cfg {
+        for (key, value) in InstanceCfg::parse(&previous).entries {
+            if !managed.iter().any(|(k, _)| *k == key) {
+                cfg.set(&key, value);
+            }
+        }
+    }
+    for (key, value) in managed {
+        cfg.set(key, value);
+    }
+
+    fs::write(&path, cfg.render()).context("Failed to write instance.cfg")
+}
+
+fn write_mmc_pack(dir: &Path, info: &PackInfo) -> Result<()> {
+    let path = dir.join(MMC_PACK);
+    let mut pack: Value = match path.is_file() {
+        true => {
+            let contents = fs::read_to_string(&path).context("Failed to read mmc-pack.json")?;
+            serde_json::from_str(&contents).context("Failed to parse mmc-pack.json")?
+        }
+        false => json!({ "components": [], "formatVersion": 1 }),
+    };
+    if !pack["components"].is_array() {
+        pack["components"] = json!([]);
+    }
+    let components = pack["components"].as_array_mut().unwrap();
+
+    if let Some(minecraft) = &info.minecraft {
+        set_component(components, "net.minecraft", minecraft, true);
+    }
+    if let Some((loader, version)) = &info.loader {
+        let uid = loader.component_uid();
+        components.retain(|c| {
+            let other = c["uid"].as_str().unwrap_or_default();
+            other == uid || !is_loader_uid(other)
+        });
+        set_component(components, uid, version, false);
+    }
+
+    let contents =
+        serde_json::to_string_pretty(&pack).context("Failed to serialize mmc-pack.json")?;
+    fs::write(&path, contents).context("Failed to write mmc-pack.json")
+}
+
+fn is_loader_uid(uid: &str) -> bool {
+    [
+        "net.minecraftforge",
+        "net.neoforged",
+        "net.fabricmc.fabric-loader",
+        "org.quiltmc.quilt-loader",
+    ]
+    .contains(&uid)
+}
+
+fn set_component(components: &mut Vec<Value>, uid: &str, version: &str, important: bool) {
+    match components.iter_mut().find(|c| c["uid"] == uid) {
+        Some(component) => {
+            if component["version"] != version {
+                // Prism refills these for the new version.
+                if let Some(fields) = component.as_object_mut() {
+                    fields.retain(|k, _| !k.starts_with("cached"));
+                }
+                component["version"] = json!(version);
+            }
+        }
+        None => {
+            let mut component = json!({ "uid": uid, "version": version });
+            if important {
+                component["important"] = json!(true);
+            }
+            components.push(component);
+        }
+    }
+}
+
+// `instgroups.json` lives next to the instances; moves the instance to `group`,
+// or just drops it from every group when `group` is `None`.
+fn update_groups(dir: &Path, group: Option<&str>) -> Result<()> {
+    let (Some(instances_dir), Some(dir_name)) = (dir.parent(), dir.file_name()) else {
+        return Ok(());
+    };
+    let dir_name = dir_name.to_string_lossy().into_owned();
+    let path = instances_dir.join(GROUPS_FILE);
+
+    let mut groups: Value = match path.is_file() {
+        true => {
+            let contents = fs::read_to_string(&path).context("Failed to read instgroups.json")?;
+            serde_json::from_str(&contents).context("Failed to parse instgroups.json")?
+        }
+        false if group.is_none() => return Ok(()),
+        false => json!({ "formatVersion": "1", "groups": {} }),
+    };
+    if !groups["groups"].is_object() {
+        groups["groups"] = json!({});
+    }
+    let map = groups["groups"].as_object_mut().unwrap();
+
+    for entry in map.values_mut() {
+        if let Some(instances) = entry["instances"].as_array_mut() {
+            instances.retain(|i| i != dir_name.as_str());
+        }
+    }
+    map.retain(|_, entry| {
+        entry["instances"]
+            .as_array()
+            .is_some_and(|instances| !instances.is_empty())
+    });
+    if let Some(group) = group {
+        let entry = map
+            .entry(group.to_string())
+            .or_insert_with(|| json!({ "hidden": false, "instances": [] }));
+        if let Some(instances) = entry["instances"].as_array_mut() {
+            instances.push(json!(dir_name));
+        }
+    }
+
+    let contents =
+        serde_json::to_string_pretty(&groups).context("Failed to serialize instgroups.json")?;
+    fs::write(&path, contents).context("Failed to write instgroups.json")
+}
+
+// Prism keeps icons in `<data dir>/icons`, one level above the instances.
+fn icons_dir(dir: &Path) -> Option<PathBuf> {
+    Some(dir.parent()?.parent()?.join("icons"))
+}
+
+fn install_icon(dir: &Path, name: &str, settings: &PrismSettings) -> Result<Option<String>> {
+    let Some(icon) = &settings.icon else {
+        return Ok(None);
+    };
+    let source = Path::new(icon);
+    if !source.is_file() {
+        // Already an icon key Prism knows.
+        return Ok(Some(icon.clone()));
+    }
+
+    let icons = icons_dir(dir).context("Could not locate Prism's icons directory")?;
+    fs::create_dir_all(&icons).context("Failed to create Prism icons directory")?;
+    let key = pack::managed_key(name);
+    fs::copy(source, icons.join(format!("{key}.png"))).context("Failed to copy instance icon")?;
+    Ok(Some(key))
+}
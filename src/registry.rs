@@ -0,0 +1,61 @@
+use crate::launcher::Launcher;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const REGISTRY_FILE: &str = "instances.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceRecord {
+    pub name: String,
+    pub launcher: Launcher,
+    pub path: PathBuf,
+    // Release tag or snapshot the instance was last installed from.
+    pub source: Option<String>,
+    // Release tag updates install instead of the latest one, set with `--tag`.
+    #[serde(default)]
+    pub pinned: Option<String>,
+    pub updated_at: String,
+}
+
+// Every instance the manager has installed, so several can live side by side.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Registry {
+    pub instances: Vec<InstanceRecord>,
+}
+
+impl Registry {
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let path = data_dir.join(REGISTRY_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path).context("Failed to read instance registry")?;
+        serde_json::from_str(&contents).context("Failed to parse instance registry")
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<()> {
+        fs::create_dir_all(data_dir).context("Failed to create data directory")?;
+        let contents =
+            serde_json::to_string_pretty(self).context("Failed to serialize instance registry")?;
+        fs::write(data_dir.join(REGISTRY_FILE), contents)
+            .context("Failed to write instance registry")
+    }
+
+    pub fn find(&self, name: &str) -> Vec<&InstanceRecord> {
+        self.instances.iter().filter(|i| i.name == name).collect()
+    }
+
+    pub fn upsert(&mut self, record: InstanceRecord) {
+        match self.instances.iter_mut().find(|i| i.path == record.path) {
+            Some(existing) => *existing = record,
+            None => self.instances.push(record),
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        self.instances.retain(|i| i.path != path);
+    }
+}
@@ -0,0 +1,55 @@
+use anyhow::{bail, Result};
+use std::fmt;
+use std::str::FromStr;
+
+// Repository releases come from unless the config or `--repo` says otherwise,
+// overridable at build time for forks and other seasons.
+const DEFAULT_REPOSITORY: &str = match option_env!("ORIGINALIFE_REPO") {
+    Some(repo) => repo,
+    None => "thebearodactyl/originalife-s4",
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    // GitLab groups can be nested, `group/subgroup`.
+    pub owner: String,
+    pub name: String,
+}
+
+impl Repository {
+    pub fn is_nested(&self) -> bool {
+        self.owner.contains('/')
+    }
+}
+
+pub fn repository(flag: Option<&str>, configured: Option<&str>) -> Result<Repository> {
+    flag.or(configured).unwrap_or(DEFAULT_REPOSITORY).parse()
+}
+
+// Accepts `owner/name`, `group/subgroup/name` or a GitHub URL.
+impl FromStr for Repository {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let path = s
+            .trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_start_matches("github.com/")
+            .trim_end_matches('/')
+            .trim_end_matches(".git");
+        match path.rsplit_once('/') {
+            Some((owner, name)) if !path.split('/').any(str::is_empty) => Ok(Self {
+                owner: owner.to_string(),
+                name: name.to_string(),
+            }),
+            _ => bail!("Invalid repository '{}', expected 'owner/name'", s),
+        }
+    }
+}
+
+impl fmt::Display for Repository {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.name)
+    }
+}
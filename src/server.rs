@@ -0,0 +1,321 @@
+use crate::extract;
+use crate::pack::{Loader, PackInfo};
+use crate::preserve;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::Command;
+use zip::{ZipArchive, ZipWriter};
+
+// Release asset listing client-only mods, one file name glob per line.
+pub const CLIENT_ONLY_LIST: &str = "client-only.txt";
+// Records which server launcher is installed, so updates only reinstall it on a version change.
+const LAUNCHER_STAMP: &str = ".originalife-server.json";
+
+// Pack files a dedicated server has no use for.
+const CLIENT_FILES: &[&str] = &[
+    "resourcepacks/**",
+    "shaderpacks/**",
+    "options.txt",
+    "optionsof.txt",
+    "optionsshaders.txt",
+    "servers.dat",
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    // Extra client-only mods, as globs on the jar file name.
+    pub client_only: Vec<String>,
+    // Java used to run the Forge, NeoForge and Quilt installers.
+    pub java: String,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            client_only: Vec::new(),
+            java: "java".to_string(),
+        }
+    }
+}
+
+#[derive(PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    minecraft: String,
+    loader: Option<(Loader, String)>,
+}
+
+// Globs from the release's list, ignoring blank lines and `#` comments.
+pub fn parse_client_only(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+// Rebuilds the client pack without client-only files and mods. Mods count as
+// client-only when listed in `client_only` or when their own metadata says so.
+pub fn server_pack(content: &[u8], client_only: &[String]) -> Result<Vec<u8>> {
+    let mut archive =
+        ZipArchive::new(io::Cursor::new(content)).context("Failed to open pack archive")?;
+    let entries = extract::scan(&mut archive)?;
+    let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+    let mut skipped = 0;
+
+    for entry in &entries {
+        let path = preserve::game_relative(&entry.path);
+        if CLIENT_FILES.iter().any(|p| preserve::glob_match(p, &path)) {
+            continue;
+        }
+        if let Some(jar) = path.strip_prefix("mods/").filter(|j| j.ends_with(".jar")) {
+            let listed = client_only.iter().any(|p| preserve::glob_match(p, jar));
+            if listed || declares_client_only(&mut archive, entry)? {
+                println!("Skipping client-only mod {}", jar);
+                skipped += 1;
+                continue;
+            }
+        }
+        writer
+            .raw_copy_file(archive.by_index_raw(entry.index)?)
+            .with_context(|| format!("Failed to copy '{}'", entry.path.display()))?;
+    }
+
+    println!("Left out {} client-only mod(s)", skipped);
+    let content = writer
+        .finish()
+        .context("Failed to build server pack")?
+        .into_inner();
+    Ok(content)
+}
+
+// Fabric and Quilt declare an environment, Forge and NeoForge have `clientSideOnly`.
+fn declares_client_only(
+    archive: &mut ZipArchive<io::Cursor<&[u8]>>,
+    entry: &extract::Entry,
+) -> Result<bool> {
+    let mut jar = Vec::with_capacity(entry.size as usize);
+    archive
+        .by_index(entry.index)?
+        .read_to_end(&mut jar)
+        .with_context(|| format!("Failed to read '{}'", entry.path.display()))?;
+    let Ok(mut jar) = ZipArchive::new(io::Cursor::new(jar)) else {
+        return Ok(false);
+    };
+
+    let mut read = |name: &str| -> Option<String> {
+        let mut file = jar.by_name(name).ok()?;
+        let mut text = String::new();
+        file.read_to_string(&mut text).ok()?;
+        Some(text)
+    };
+    let json = |text: Option<String>| -> Option<Value> { serde_json::from_str(&text?).ok() };
+
+    if let Some(fabric) = json(read("fabric.mod.json")) {
+        return Ok(fabric["environment"] == "client");
+    }
+    if let Some(quilt) = json(read("quilt.mod.json")) {
+        return Ok(quilt["minecraft"]["environment"] == "client");
+    }
+    for toml in ["META-INF/neoforge.mods.toml", "META-INF/mods.toml"] {
+        if let Some(text) = read(toml) {
+            return Ok(text.lines().any(|line| {
+                line.split_once('=').is_some_and(|(key, value)| {
+                    key.trim() == "clientSideOnly" && value.trim() == "true"
+                })
+            }));
+        }
+    }
+    Ok(false)
+}
+
+// Installs the server launcher for the pack's Minecraft and loader versions,
+// unless the same one is already there.
+pub async fn install_launcher(
+    dir: &Path,
+    info: &PackInfo,
+    settings: &ServerSettings,
+) -> Result<()> {
+    let minecraft = info.require_minecraft()?.to_string();
+    let stamp = Stamp {
+        minecraft,
+        loader: info.loader.clone(),
+    };
+    let stamp_path = dir.join(LAUNCHER_STAMP);
+    let installed = fs::read_to_string(&stamp_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<Stamp>(&s).ok());
+    if installed.as_ref() == Some(&stamp) {
+        return Ok(());
+    }
+
+    let client = reqwest::Client::new();
+    let minecraft = stamp.minecraft.as_str();
+    let start = match &stamp.loader {
+        None => {
+            let manifest = fetch_json(
+                &client,
+                "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json",
+            )
+            .await?;
+            let version_url = manifest["versions"]
+                .as_array()
+                .and_then(|v| v.iter().find(|v| v["id"] == minecraft))
+                .and_then(|v| v["url"].as_str())
+                .with_context(|| format!("Unknown Minecraft version {minecraft}"))?;
+            let version = fetch_json(&client, version_url).await?;
+            let server_url = version["downloads"]["server"]["url"]
+                .as_str()
+                .with_context(|| format!("Minecraft {minecraft} has no server download"))?;
+            download(&client, server_url, &dir.join("server.jar")).await?;
+            "java -jar server.jar nogui"
+        }
+        Some((Loader::Fabric, loader)) => {
+            let installers =
+                fetch_json(&client, "https://meta.fabricmc.net/v2/versions/installer").await?;
+            let installer = installers
+                .as_array()
+                .and_then(|i| i.iter().find(|i| i["stable"] == true))
+                .and_then(|i| i["version"].as_str())
+                .context("Failed to find a Fabric installer version")?;
+            let url = format!(
+                "https://meta.fabricmc.net/v2/versions/loader/{minecraft}/{loader}/{installer}/server/jar"
+            );
+            download(&client, &url, &dir.join("fabric-server-launch.jar")).await?;
+            "java -jar fabric-server-launch.jar nogui"
+        }
+        Some((Loader::Quilt, loader)) => {
+            let installers =
+                fetch_json(&client, "https://meta.quiltmc.org/v3/versions/installer").await?;
+            let url = installers
+                .as_array()
+                .and_then(|i| i.first())
+                .and_then(|i| i["url"].as_str())
+                .context("Failed to find a Quilt installer version")?;
+            run_installer(&client, url, dir, settings, |installer| {
+                vec![
+                    "-jar".into(),
+                    installer.into(),
+                    "install".into(),
+                    "server".into(),
+                    minecraft.into(),
+                    loader.into(),
+                    format!("--install-dir={}", dir.display()),
+                    "--download-server".into(),
+                ]
+            })
+            .await?;
+            "java -jar quilt-server-launch.jar nogui"
+        }
+        Some((Loader::Forge, loader)) => {
+            let version = format!("{minecraft}-{loader}");
+            let url = format!(
+                "https://maven.minecraftforge.net/net/minecraftforge/forge/{version}/forge-{version}-installer.jar"
+            );
+            run_installer(&client, &url, dir, settings, |installer| {
+                vec![
+                    "-jar".into(),
+                    installer.into(),
+                    "--installServer".into(),
+                    dir.display().to_string(),
+                ]
+            })
+            .await?;
+            "./run.sh (run.bat on Windows)"
+        }
+        Some((Loader::NeoForge, loader)) => {
+            let url = format!(
+                "https://maven.neoforged.net/releases/net/neoforged/neoforge/{loader}/neoforge-{loader}-installer.jar"
+            );
+            run_installer(&client, &url, dir, settings, |installer| {
+                vec![
+                    "-jar".into(),
+                    installer.into(),
+                    "--installServer".into(),
+                    dir.display().to_string(),
+                ]
+            })
+            .await?;
+            "./run.sh (run.bat on Windows)"
+        }
+    };
+
+    let contents =
+        serde_json::to_string_pretty(&stamp).context("Failed to serialize server stamp")?;
+    fs::write(&stamp_path, contents).context("Failed to write server stamp")?;
+
+    println!(
+        "Installed the {} server launcher, start the server with {}",
+        describe(&stamp),
+        start
+    );
+    if !dir.join("eula.txt").is_file() {
+        println!(
+            "Accept the Minecraft EULA by putting `eula=true` in eula.txt before the first start."
+        );
+    }
+    Ok(())
+}
+
+fn describe(stamp: &Stamp) -> String {
+    match &stamp.loader {
+        Some((loader, version)) => format!("Minecraft {} {} {}", stamp.minecraft, loader, version),
+        None => format!("Minecraft {}", stamp.minecraft),
+    }
+}
+
+async fn fetch_json(client: &reqwest::Client, url: &str) -> Result<Value> {
+    let text = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to fetch {url}"))?
+        .text()
+        .await
+        .with_context(|| format!("Failed to read {url}"))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse {url}"))
+}
+
+async fn download(client: &reqwest::Client, url: &str, to: &Path) -> Result<()> {
+    let bytes = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .with_context(|| format!("Failed to download {url}"))?
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read {url}"))?;
+    fs::write(to, bytes).with_context(|| format!("Failed to write {}", to.display()))
+}
+
+// Downloads an installer jar next to the server, runs it with `args` and removes it again.
+async fn run_installer(
+    client: &reqwest::Client,
+    url: &str,
+    dir: &Path,
+    settings: &ServerSettings,
+    args: impl FnOnce(&str) -> Vec<String>,
+) -> Result<()> {
+    fs::create_dir_all(dir).context("Failed to create server directory")?;
+    let installer = dir.join("originalife-installer.jar");
+    download(client, url, &installer).await?;
+
+    println!("Running the loader installer, this can take a while...");
+    let status = Command::new(&settings.java)
+        .args(args(&installer.to_string_lossy()))
+        .current_dir(dir)
+        .status()
+        .with_context(|| format!("Failed to run '{}', is Java installed?", settings.java));
+    fs::remove_file(&installer).context("Failed to remove the loader installer")?;
+    if !status?.success() {
+        bail!("The loader installer failed");
+    }
+    Ok(())
+}
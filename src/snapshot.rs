@@ -0,0 +1,119 @@
+use crate::install;
+use crate::manifest::{self, Manifest};
+use crate::preserve::PreservePolicy;
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+pub struct Snapshot {
+    pub id: String,
+    pub path: PathBuf,
+}
+
+// Archives everything in the instance that an update may replace, i.e. all
+// pack-managed files including `config/`. Preserved player data is left out,
+// the install's manifest goes along so a restore records the same pack files.
+pub fn create(target: &Path, dir: &Path, policy: &PreservePolicy) -> Result<Option<Snapshot>> {
+    let installed = Manifest::load(target)?;
+    let files: Vec<PathBuf> = install::existing_files(target)?
+        .into_iter()
+        .filter(|file| {
+            let in_pack = installed.as_ref().is_some_and(|m| m.get(file).is_some());
+            !policy.is_preserved(file, in_pack)
+        })
+        .collect();
+    if files.is_empty() {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(dir).context("Failed to create snapshot directory")?;
+    let id = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let path = dir.join(format!("{id}.zip"));
+
+    let out = fs::File::create(&path).context("Failed to create snapshot file")?;
+    let mut zip = ZipWriter::new(out);
+    let options = SimpleFileOptions::default().large_file(true);
+    for file in &files {
+        zip.start_file(manifest::entry_name(file), options)?;
+        let mut input = fs::File::open(target.join(file))
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        io::copy(&mut input, &mut zip)?;
+    }
+    if let Some(installed) = &installed {
+        zip.start_file(manifest::MANIFEST_FILE, options)?;
+        serde_json::to_writer(&mut zip, installed).context("Failed to write snapshot manifest")?;
+    }
+    zip.finish().context("Failed to write snapshot file")?;
+
+    Ok(Some(Snapshot { id, path }))
+}
+
+// Oldest first.
+pub fn list(dir: &Path) -> Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+    if !dir.exists() {
+        return Ok(snapshots);
+    }
+
+    for entry in fs::read_dir(dir).context("Failed to read snapshot directory")? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "zip") {
+            if let Some(id) = path.file_stem().map(|s| s.to_string_lossy().into_owned()) {
+                snapshots.push(Snapshot { id, path });
+            }
+        }
+    }
+    snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(snapshots)
+}
+
+pub fn prune(dir: &Path, keep: usize) -> Result<()> {
+    let snapshots = list(dir)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    for snapshot in &snapshots[..excess] {
+        fs::remove_file(&snapshot.path)
+            .with_context(|| format!("Failed to remove snapshot {}", snapshot.id))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::launcher::Launcher;
+
+    #[test]
+    fn restore_keeps_the_installed_manifest() {
+        let root = std::env::temp_dir().join(format!("snapshot-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let target = root.join("instance");
+        fs::create_dir_all(target.join("mods")).unwrap();
+        fs::write(target.join("mods/pack.jar"), "pack").unwrap();
+        fs::write(target.join("mods/extra.jar"), "added by the player").unwrap();
+        let installed = Manifest {
+            files: [("mods/pack.jar".to_string(), "hash".to_string())].into(),
+        };
+        fs::write(
+            target.join(manifest::MANIFEST_FILE),
+            serde_json::to_string(&installed).unwrap(),
+        )
+        .unwrap();
+
+        let policy = PreservePolicy::new(&[], Launcher::Prism);
+        let snapshot = create(&target, &root.join("snapshots"), &policy)
+            .unwrap()
+            .unwrap();
+        fs::remove_file(target.join(manifest::MANIFEST_FILE)).unwrap();
+        install::install(&snapshot.path, &target, &policy, false).unwrap();
+
+        let restored = Manifest::load(&target).unwrap().unwrap();
+        let extra = fs::read_to_string(target.join("mods/extra.jar")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert_eq!(restored.files, installed.files);
+        assert_eq!(extra, "added by the player");
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::Config;
+use crate::gitea::Gitea;
+use crate::github::GitHub;
+use crate::gitlab::GitLab;
+use crate::repository::Repository;
+use crate::static_index::StaticIndex;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use http::{HeaderMap, HeaderValue};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+// Where releases are looked up, `repository` names the project on the forges.
+#[derive(Debug, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SourceSettings {
+    #[default]
+    GitHub,
+    // Gitea and Forgejo share the same API.
+    #[serde(alias = "forgejo")]
+    Gitea {
+        url: String,
+        token: Option<String>,
+    },
+    GitLab {
+        #[serde(default = "gitlab_url")]
+        url: String,
+        token: Option<String>,
+    },
+    // A plain `index.json` listing the releases, see the README for its format.
+    Index {
+        url: String,
+    },
+}
+
+fn gitlab_url() -> String {
+    "https://gitlab.com".to_string()
+}
+
+pub struct Release {
+    pub tag: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub prerelease: bool,
+    pub draft: bool,
+    pub assets: Vec<Asset>,
+}
+
+pub struct Asset {
+    pub name: String,
+    // GitLab doesn't report sizes for release links.
+    pub size: Option<u64>,
+    pub url: String,
+    // Sent with the download, e.g. the token a private repository needs.
+    pub headers: HeaderMap,
+}
+
+impl Release {
+    pub fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+pub enum Source {
+    GitHub(GitHub),
+    Gitea(Gitea),
+    GitLab(GitLab),
+    Index(StaticIndex),
+}
+
+impl Source {
+    pub fn new(repository: &Repository, config: &Config) -> Result<Self> {
+        Ok(match &config.source {
+            SourceSettings::GitHub => Self::GitHub(GitHub::new(repository, config)?),
+            SourceSettings::Gitea { url, token } => {
+                Self::Gitea(Gitea::new(url, repository, token.clone())?)
+            }
+            SourceSettings::GitLab { url, token } => {
+                Self::GitLab(GitLab::new(url, repository, token.clone()))
+            }
+            SourceSettings::Index { url } => Self::Index(StaticIndex::new(url)?),
+        })
+    }
+
+    // Nightly builds come from GitHub Actions, the other sources have none.
+    pub fn github(&self) -> Option<&GitHub> {
+        match self {
+            Self::GitHub(github) => Some(github),
+            _ => None,
+        }
+    }
+
+    pub async fn latest_release(&self) -> Result<Release> {
+        match self {
+            Self::GitHub(github) => github.latest_release().await,
+            Self::Gitea(gitea) => gitea.latest_release().await,
+            Self::GitLab(gitlab) => gitlab.latest_release().await,
+            Self::Index(index) => index.latest_release().await,
+        }
+    }
+
+    pub async fn release(&self, tag: &str) -> Result<Release> {
+        match self {
+            Self::GitHub(github) => github.release(tag).await,
+            Self::Gitea(gitea) => gitea.release(tag).await,
+            Self::GitLab(gitlab) => gitlab.release(tag).await,
+            Self::Index(index) => index.release(tag).await,
+        }
+    }
+
+    // Newest first, `pages` pages at most.
+    pub async fn releases(&self, pages: usize) -> Result<Vec<Release>> {
+        match self {
+            Self::GitHub(github) => github.releases(pages).await,
+            Self::Gitea(gitea) => gitea.releases(pages).await,
+            Self::GitLab(gitlab) => gitlab.releases(pages).await,
+            Self::Index(index) => index.releases().await,
+        }
+    }
+}
+
+// Tags and project paths go into URL paths, where `/`, `#` or `?` would change the route.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+pub fn path_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, SEGMENT).to_string()
+}
+
+// `name: value` for downloading `url`, but only when the asset is hosted by
+// the source itself. Release links may point anywhere, the token must not follow.
+pub fn token_headers(url: &str, api: &str, name: &'static str, value: Option<String>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let origin = |url: &str| reqwest::Url::parse(url).ok().map(|url| url.origin());
+    if origin(url).is_none() || origin(url) != origin(api) {
+        return headers;
+    }
+    if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert(name, value);
+    }
+    headers
+}
+
+// Sends `request` and parses the JSON answer, for the sources without a client library.
+pub async fn get_json<T: DeserializeOwned>(request: reqwest::RequestBuilder) -> Result<T> {
+    let response = request
+        .send()
+        .await
+        .context("Failed to reach the release source")?;
+    let url = response.url().clone();
+    let response = response
+        .error_for_status()
+        .with_context(|| format!("Release source returned an error for {url}"))?;
+    let body = response
+        .text()
+        .await
+        .with_context(|| format!("Failed to read {url}"))?;
+    serde_json::from_str(&body).with_context(|| format!("Failed to parse {url}"))
+}
@@ -0,0 +1,92 @@
+use crate::source::{self, Asset, Release};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use reqwest::Url;
+use serde::Deserialize;
+
+// An `index.json` on any web server, listing releases newest first. Asset URLs
+// may be relative to the index.
+pub struct StaticIndex {
+    client: reqwest::Client,
+    url: Url,
+}
+
+#[derive(Deserialize)]
+struct Index {
+    releases: Vec<IndexRelease>,
+}
+
+#[derive(Deserialize)]
+struct IndexRelease {
+    tag: String,
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<IndexAsset>,
+}
+
+#[derive(Deserialize)]
+struct IndexAsset {
+    name: String,
+    url: String,
+    size: Option<u64>,
+}
+
+impl StaticIndex {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            url: Url::parse(url).with_context(|| format!("Invalid index URL '{url}'"))?,
+        })
+    }
+
+    pub async fn releases(&self) -> Result<Vec<Release>> {
+        let index: Index = source::get_json(self.client.get(self.url.clone())).await?;
+        index
+            .releases
+            .into_iter()
+            .map(|release| {
+                let assets = release
+                    .assets
+                    .into_iter()
+                    .map(|asset| {
+                        let url = self.url.join(&asset.url).with_context(|| {
+                            format!("Invalid URL for '{}' in the index", asset.name)
+                        })?;
+                        Ok(Asset {
+                            name: asset.name,
+                            size: asset.size,
+                            url: url.to_string(),
+                            headers: HeaderMap::new(),
+                        })
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Release {
+                    tag: release.tag,
+                    published_at: release.published_at,
+                    prerelease: release.prerelease,
+                    draft: false,
+                    assets,
+                })
+            })
+            .collect()
+    }
+
+    pub async fn latest_release(&self) -> Result<Release> {
+        self.releases()
+            .await?
+            .into_iter()
+            .find(|r| !r.prerelease)
+            .context("The index lists no release")
+    }
+
+    pub async fn release(&self, tag: &str) -> Result<Release> {
+        self.releases()
+            .await?
+            .into_iter()
+            .find(|r| r.tag == tag)
+            .with_context(|| format!("The index has no release '{tag}'"))
+    }
+}
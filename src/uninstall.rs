@@ -0,0 +1,87 @@
+use crate::install;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Player data worth keeping when an instance is removed, `world` being a server's.
+const EXPORTED: &[&str] = &["saves", "screenshots", "world"];
+
+// Directories under `target` that would be exported, relative to the game directory.
+pub fn exportable(target: &Path) -> Vec<PathBuf> {
+    let game_dirs = [target.join(".minecraft"), target.join("minecraft")];
+    let game_dir = game_dirs
+        .into_iter()
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| target.to_path_buf());
+
+    EXPORTED
+        .iter()
+        .map(|name| game_dir.join(name))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+pub fn export(target: &Path, export_dir: &Path) -> Result<()> {
+    for dir in exportable(target) {
+        let name = dir.file_name().context("Export directory has no name")?;
+        let destination = export_dir.join(name);
+        fs::create_dir_all(export_dir).context("Failed to create export directory")?;
+        // Renaming fails across drives, fall back to copying.
+        if fs::rename(&dir, &destination).is_err() {
+            copy_dir(&dir, &destination)
+                .with_context(|| format!("Failed to export {}", dir.display()))?;
+        }
+        println!("Exported {} to {}", dir.display(), destination.display());
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let destination = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn remove_instance(target: &Path) -> Result<()> {
+    install::discard_leftovers(target)?;
+    if target.exists() {
+        fs::remove_dir_all(target)
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+        println!("Removed {}", target.display());
+    }
+    Ok(())
+}
+
+// Downloads left behind by interrupted updates and restores.
+pub fn cached_downloads() -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(".").context("Failed to read the working directory")? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let cached = (name.starts_with("updated-pack-") || name.starts_with("snapshot-"))
+            && name.ends_with(".zip");
+        if cached && path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+pub fn remove_files(files: &[PathBuf]) -> Result<()> {
+    for file in files {
+        fs::remove_file(file).with_context(|| format!("Failed to remove {}", file.display()))?;
+        println!("Removed {}", file.display());
+    }
+    Ok(())
+}
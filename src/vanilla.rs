@@ -0,0 +1,84 @@
+use crate::pack::{self, Loader, PackInfo};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const PROFILES_FILE: &str = "launcher_profiles.json";
+
+// Adds or refreshes a custom profile whose game directory is the instance.
+pub fn register(dir: &Path, name: &str, info: &PackInfo) -> Result<()> {
+    let Some(path) = profiles_file(dir) else {
+        println!("launcher_profiles.json not found, start the Minecraft Launcher once and run the update again.");
+        return Ok(());
+    };
+    let version_id = version_id(info.require_minecraft()?, info);
+    let game_dir = std::path::absolute(dir).context("Failed to resolve instance directory")?;
+
+    let mut profiles = read_profiles(&path)?;
+    if !profiles["profiles"].is_object() {
+        profiles["profiles"] = json!({});
+    }
+    let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let entry = profiles["profiles"]
+        .as_object_mut()
+        .unwrap()
+        .entry(pack::managed_key(name))
+        .or_insert_with(|| json!({ "created": now, "icon": "Furnace" }));
+    entry["name"] = json!(name);
+    entry["type"] = json!("custom");
+    entry["gameDir"] = json!(game_dir);
+    entry["lastVersionId"] = json!(version_id);
+    entry["lastUsed"] = json!(now);
+
+    write_profiles(&path, &profiles)?;
+
+    // The launcher only runs loaders whose version JSON it already has.
+    let versions = path.with_file_name("versions").join(&version_id);
+    if info.loader.is_some() && !versions.is_dir() {
+        println!("Version {version_id} is not installed, run the loader's installer for this Minecraft Launcher before playing.");
+    }
+    Ok(())
+}
+
+pub fn unregister(dir: &Path, name: &str) -> Result<()> {
+    let Some(path) = profiles_file(dir) else {
+        return Ok(());
+    };
+    let mut profiles = read_profiles(&path)?;
+    let removed = profiles["profiles"]
+        .as_object_mut()
+        .and_then(|p| p.remove(&pack::managed_key(name)));
+    if removed.is_some() {
+        write_profiles(&path, &profiles)?;
+    }
+    Ok(())
+}
+
+// `<.minecraft>/instances/<name>` -> `<.minecraft>/launcher_profiles.json`
+fn profiles_file(dir: &Path) -> Option<PathBuf> {
+    let data_dir = dir.parent()?.parent()?;
+    Some(data_dir.join(PROFILES_FILE)).filter(|p| p.is_file())
+}
+
+fn read_profiles(path: &Path) -> Result<Value> {
+    let contents = fs::read_to_string(path).context("Failed to read launcher_profiles.json")?;
+    serde_json::from_str(&contents).context("Failed to parse launcher_profiles.json")
+}
+
+fn write_profiles(path: &Path, profiles: &Value) -> Result<()> {
+    let contents = serde_json::to_string_pretty(profiles)
+        .context("Failed to serialize launcher_profiles.json")?;
+    fs::write(path, contents).context("Failed to write launcher_profiles.json")
+}
+
+// Version ids as the loaders' own installers name them.
+fn version_id(minecraft: &str, info: &PackInfo) -> String {
+    match &info.loader {
+        None => minecraft.to_string(),
+        Some((Loader::Fabric, version)) => format!("fabric-loader-{version}-{minecraft}"),
+        Some((Loader::Quilt, version)) => format!("quilt-loader-{version}-{minecraft}"),
+        Some((Loader::Forge, version)) => format!("{minecraft}-forge-{version}"),
+        Some((Loader::NeoForge, version)) => format!("neoforge-{version}"),
+    }
+}
@@ -0,0 +1,214 @@
+use crate::manifest;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use blake2::{Blake2b512, Digest};
+use ring::signature::{UnparsedPublicKey, ED25519};
+
+// Release assets that may list the SHA-256 of every artifact, in `sha256sum` format.
+const CHECKSUM_ASSETS: &[&str] = &[
+    "SHA256SUMS",
+    "SHA256SUMS.txt",
+    "sha256sums.txt",
+    "checksums.txt",
+];
+
+// Prefers a per-artifact `<artifact>.sha256` over a combined list.
+pub fn checksum_file<'a>(names: &[&'a str], artifact: &str) -> Option<&'a str> {
+    let own = format!("{artifact}.sha256");
+    names.iter().find(|n| **n == own).copied().or_else(|| {
+        CHECKSUM_ASSETS
+            .iter()
+            .find_map(|name| names.iter().find(|n| *n == name).copied())
+    })
+}
+
+pub fn expected_checksum(contents: &str, artifact: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let hash = parts.next()?;
+        if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match parts.next() {
+            // A bare hash, as found in `<artifact>.sha256`.
+            None => Some(hash.to_ascii_lowercase()),
+            Some(name) => {
+                let name = name.trim_start_matches('*').trim_start_matches("./");
+                (name == artifact).then(|| hash.to_ascii_lowercase())
+            }
+        }
+    })
+}
+
+pub fn verify_size(content: &[u8], expected: u64, artifact: &str) -> Result<()> {
+    if content.len() as u64 != expected {
+        bail!(
+            "Downloaded '{}' is {} bytes but the release lists {} bytes, refusing to install it",
+            artifact,
+            content.len(),
+            expected
+        );
+    }
+    Ok(())
+}
+
+pub fn verify_checksum(content: &[u8], expected: &str, artifact: &str) -> Result<()> {
+    let actual = manifest::hash_reader(&mut &content[..])?;
+    if actual != expected {
+        bail!(
+            "Checksum mismatch for '{}': expected {}, got {}. The download may be corrupted or tampered with, nothing was changed",
+            artifact,
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+// The key the release maintainers sign with, baked in at build time.
+const EMBEDDED_PUBLIC_KEY: Option<&str> = option_env!("ORIGINALIFE_PUBLIC_KEY");
+
+pub fn public_key(configured: Option<&str>) -> Result<Option<PublicKey>> {
+    configured
+        .or(EMBEDDED_PUBLIC_KEY)
+        .map(parse_public_key)
+        .transpose()
+}
+
+// Accepts the contents of a minisign `.pub` file or just its base64 line.
+pub fn parse_public_key(text: &str) -> Result<PublicKey> {
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with("untrusted comment:"))
+        .context("Public key is empty")?;
+    let bytes = BASE64
+        .decode(line)
+        .context("Public key is not valid base64")?;
+    if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+        bail!("Public key is not a minisign Ed25519 key");
+    }
+
+    Ok(PublicKey {
+        key_id: bytes[2..10].try_into()?,
+        key: bytes[10..].try_into()?,
+    })
+}
+
+pub fn signature_file(artifact: &str) -> String {
+    format!("{artifact}.minisig")
+}
+
+// Checks a minisign signature file against `content`, returning its trusted comment.
+pub fn verify_signature(content: &[u8], signature: &str, key: &PublicKey) -> Result<String> {
+    let mut lines = signature.lines().map(str::trim).filter(|l| !l.is_empty());
+    let _untrusted = lines.next().context("Signature file is empty")?;
+    let signature_line = lines.next().context("Signature file has no signature")?;
+    let trusted_comment = lines
+        .next()
+        .and_then(|l| l.strip_prefix("trusted comment: "))
+        .context("Signature file has no trusted comment")?;
+    let global_line = lines
+        .next()
+        .context("Signature file has no global signature")?;
+
+    let signature = BASE64
+        .decode(signature_line)
+        .context("Signature is not valid base64")?;
+    if signature.len() != 74 {
+        bail!("Signature is not a minisign Ed25519 signature");
+    }
+    let (algorithm, key_id, signature) = (&signature[..2], &signature[2..10], &signature[10..]);
+    if key_id != key.key_id {
+        bail!("Release was signed with a different key than the one configured");
+    }
+
+    let verifier = UnparsedPublicKey::new(&ED25519, &key.key);
+    let verified = match algorithm {
+        b"Ed" => verifier.verify(content, signature),
+        b"ED" => verifier.verify(&Blake2b512::digest(content), signature),
+        _ => bail!("Unsupported signature algorithm"),
+    };
+    verified.map_err(|_| anyhow!("Signature verification failed, the release may have been tampered with. Nothing was changed"))?;
+
+    let global = BASE64
+        .decode(global_line)
+        .context("Global signature is not valid base64")?;
+    let signed_comment = [signature, trusted_comment.as_bytes()].concat();
+    verifier
+        .verify(&signed_comment, &global)
+        .map_err(|_| anyhow!("The signature's trusted comment has been tampered with"))?;
+
+    Ok(trusted_comment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Made with an Ed25519 key outside this crate, in minisign's formats.
+    const PUBLIC_KEY: &str = "untrusted comment: minisign public key 0807060504030201
+RWQBAgMEBQYHCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4
+";
+    const CONTENT: &[u8] = b"originalife test release\n";
+    // Pre-hashed with BLAKE2b, what `minisign -S` writes by default.
+    const PREHASHED: &str = "untrusted comment: signature from minisign secret key
+RUQBAgMEBQYHCALylD+N1k+B+mZV04a5kSAd740tOSwQU+srgfvaPGuJ5xUeokTy98/F5hfaqpX5119n6dYJiUGlWTNqeaEQPg4=
+trusted comment: timestamp:1700000000\tfile:updated-pack-prism.zip
+ksiPK5Uhahoos54/79FsONNX02nZd8FHt3B7795gEO8smx6v3EHbzcbK+Phs7Y/1fDfmGL/cVq/25vr57W+ZDg==
+";
+    // The legacy format signing the content itself, `minisign -S -l`.
+    const LEGACY: &str = "untrusted comment: signature from minisign secret key
+RWQBAgMEBQYHCK5ed8gIDwmJ2HKwpS8w/n095MdrFP35+nMQD3YEe5WKGne5k7Bs5ST6qom2LgKzU2ULPZySrprM59nTPQEpWQ4=
+trusted comment: timestamp:1700000000\tfile:updated-pack-prism.zip
+apUzJEXvg0SQRXNbPvZ2AuPwKlRt6Nlmvf3gt1ZFAVdXiBEMs49IXfRVVvNIjZ2U8inAJOGvxypyayIj2/2QAQ==
+";
+
+    fn key() -> PublicKey {
+        parse_public_key(PUBLIC_KEY).unwrap()
+    }
+
+    #[test]
+    fn verifies_prehashed_signatures() {
+        let comment = verify_signature(CONTENT, PREHASHED, &key()).unwrap();
+        assert_eq!(comment, "timestamp:1700000000\tfile:updated-pack-prism.zip");
+    }
+
+    #[test]
+    fn verifies_legacy_signatures() {
+        assert!(verify_signature(CONTENT, LEGACY, &key()).is_ok());
+    }
+
+    #[test]
+    fn rejects_changed_content() {
+        assert!(verify_signature(b"originalife test release!\n", PREHASHED, &key()).is_err());
+        assert!(verify_signature(b"originalife test release!\n", LEGACY, &key()).is_err());
+    }
+
+    #[test]
+    fn rejects_changed_trusted_comment() {
+        let signature = PREHASHED.replace("1700000000", "1800000000");
+        assert!(verify_signature(CONTENT, &signature, &key()).is_err());
+    }
+
+    #[test]
+    fn rejects_other_keys() {
+        let mut other = key();
+        other.key_id[0] ^= 1;
+        assert!(verify_signature(CONTENT, PREHASHED, &other).is_err());
+    }
+
+    #[test]
+    fn parses_bare_public_keys() {
+        let bare =
+            parse_public_key("RWQBAgMEBQYHCAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4").unwrap();
+        assert_eq!(bare.key, key().key);
+        assert!(parse_public_key("untrusted comment: nothing\n").is_err());
+    }
+}